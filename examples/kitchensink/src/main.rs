@@ -27,7 +27,7 @@ fn main() {
 
     let mut step = 0;
     while {
-        input.update(&mut drawer);
+        input.update(&mut drawer).expect("Failed to read input");
         input.do_continue()
     } {
         step += 1;
@@ -79,6 +79,6 @@ fn main() {
             },
         );
 
-        drawer.end();
+        drawer.end().expect("Failed to present frame");
     }
 }
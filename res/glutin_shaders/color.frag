@@ -1,9 +1,16 @@
 #version 150 core
 
+// If the output should be premultiplied by alpha
+uniform bool premultiply;
+
 in vec4 output_color;
 
 out vec4 outColor;
 
 void main() {
-    outColor = output_color;
+    if (premultiply) {
+        outColor = vec4(output_color.rgb * output_color.a, output_color.a);
+    } else {
+        outColor = output_color;
+    }
 }
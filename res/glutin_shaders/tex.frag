@@ -2,11 +2,27 @@
 
 uniform sampler2D bind_tex;
 
+// If the output should be premultiplied by alpha, and if the texture already is
+uniform bool premultiply;
+uniform bool premultiplied_tex;
+
 in vec2 output_uv;
 in vec4 output_color;
 
 out vec4 outColor;
 
 void main() {
-    outColor = texture2D(bind_tex, output_uv) * output_color;
-}
\ No newline at end of file
+    vec4 texel = texture2D(bind_tex, output_uv);
+    vec4 color = output_color;
+
+    if (premultiply) {
+        if (!premultiplied_tex) {
+            texel.rgb *= texel.a;
+        }
+        color.rgb *= color.a;
+    } else if (premultiplied_tex && texel.a > 0.0) {
+        texel.rgb /= texel.a;
+    }
+
+    outColor = texel * color;
+}
@@ -1,5 +1,12 @@
+// If the output should be premultiplied by alpha
+uniform bool premultiply;
+
 varying vec4 output_color;
 
 void main() {
-    gl_FragColor = output_color;
+    if (premultiply) {
+        gl_FragColor = vec4(output_color.rgb * output_color.a, output_color.a);
+    } else {
+        gl_FragColor = output_color;
+    }
 }
@@ -1,8 +1,29 @@
 uniform sampler2D bind_tex;
 
+// If the output should be premultiplied by alpha, and if the texture already is
+uniform bool premultiply;
+uniform bool premultiplied_tex;
+// If the texture only has alpha, which should be treated as white
+uniform bool alpha_tex;
+
 varying vec2 output_uv;
 varying vec4 output_color;
 
 void main() {
-    gl_FragColor = texture2D(bind_tex, output_uv) * output_color;
-}
\ No newline at end of file
+    vec4 texel = texture2D(bind_tex, output_uv);
+    if (alpha_tex) {
+        texel.rgb = vec3(1.0);
+    }
+    vec4 color = output_color;
+
+    if (premultiply) {
+        if (!premultiplied_tex) {
+            texel.rgb *= texel.a;
+        }
+        color.rgb *= color.a;
+    } else if (premultiplied_tex && texel.a > 0.0) {
+        texel.rgb /= texel.a;
+    }
+
+    gl_FragColor = texel * color;
+}
@@ -0,0 +1,76 @@
+//! The error type returned by everything in this crate which can fail.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Which stage of a shader program failed to compile.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+        }
+    }
+}
+
+/// Something which went wrong while setting up or drawing to the screen, or loading
+/// resources for it.
+#[derive(Debug)]
+pub enum Error {
+    /// The window or graphics context couldn't be created, or stopped working.
+    Context(String),
+    /// A shader failed to compile, with the info log from the driver.
+    ShaderCompile { stage: ShaderStage, log: String },
+    /// A shader program failed to link, with the info log from the driver.
+    ShaderLink { log: String },
+    /// A texture couldn't be created or updated.
+    Texture(String),
+    /// A font couldn't be parsed.
+    Font(String),
+    /// Reading or writing a file or device failed.
+    Io(io::Error),
+    /// Data such as a sprite sheet or display list was malformed, or referred to something
+    /// which doesn't exist.
+    InvalidData(String),
+    /// A rendered frame didn't match its snapshot baseline.
+    Snapshot(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Context(ref msg) => write!(f, "Graphics context error: {}", msg),
+            Error::ShaderCompile { stage, ref log } => {
+                write!(f, "Failed to compile {} shader: {}", stage, log)
+            }
+            Error::ShaderLink { ref log } => write!(f, "Failed to link shader program: {}", log),
+            Error::Texture(ref msg) => write!(f, "Texture error: {}", msg),
+            Error::Font(ref msg) => write!(f, "Failed to read font: {}", msg),
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::InvalidData(ref msg) => write!(f, "Invalid data: {}", msg),
+            Error::Snapshot(ref msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -2,33 +2,61 @@ use glutin;
 
 use crate::input::Input;
 
+use crate::render::glutin::drawer::GlutinContext;
 use crate::render::glutin::drawer::GlutinDrawer;
 
+use crate::Error;
+
 /// Implements a basic input abstraction for Glutin.
 pub struct GlutinInput {
     mouse_down: bool,
     mouse_x: usize,
     mouse_y: usize,
     running: bool,
+    resize: Option<(usize, usize)>,
 }
 
 impl Input for GlutinInput {
     type Window = GlutinDrawer;
 
-    fn update(&mut self, window: &mut Self::Window) {
+    fn update(&mut self, window: &mut Self::Window) -> Result<(), Error> {
+        // Mouse positions are reported in the same units as draws are made in
+        let logical_units = window.logical_units;
         let events = &mut window.events_loop;
-        let window = &window.gl_window;
+        let window = match window.context {
+            GlutinContext::Windowed(ref window) => window,
+            // Nothing to receive input from
+            GlutinContext::Headless { .. } => return Ok(()),
+        };
+
+        self.resize = None;
+        let mut resized = false;
 
         events.poll_events(|event| {
             if let glutin::Event::WindowEvent { event, .. } = event {
                 match event {
                     glutin::WindowEvent::CloseRequested => self.running = false,
-                    glutin::WindowEvent::Resized(size) => window.resize(size.to_physical(1.0)),
+                    glutin::WindowEvent::Resized(size) => {
+                        window.resize(size.to_physical(window.window().get_hidpi_factor()));
+                        resized = true;
+                    }
+                    glutin::WindowEvent::HiDpiFactorChanged(factor) => {
+                        if let Some(size) = window.window().get_inner_size() {
+                            window.resize(size.to_physical(factor));
+                            resized = true;
+                        }
+                    }
                     glutin::WindowEvent::MouseInput { state, .. } => {
                         self.mouse_down = state == glutin::ElementState::Pressed;
                     }
                     glutin::WindowEvent::CursorMoved { position, .. } => {
-                        let (x, y): (i32, i32) = position.into();
+                        let (x, y): (i32, i32) = if logical_units {
+                            position.into()
+                        } else {
+                            position
+                                .to_physical(window.window().get_hidpi_factor())
+                                .into()
+                        };
                         self.mouse_x = x as usize;
                         self.mouse_y = y as usize;
                     }
@@ -36,6 +64,21 @@ impl Input for GlutinInput {
                 }
             }
         });
+
+        if resized {
+            if let Some(size) = window.window().get_inner_size() {
+                // Rounded the same way as the drawer's own size
+                let factor = window.window().get_hidpi_factor();
+                let scale = if logical_units { factor } else { 1.0 };
+                let (width, height): (u32, u32) = size.to_physical(factor).into();
+                self.resize = Some((
+                    (f64::from(width) / scale).round() as usize,
+                    (f64::from(height) / scale).round() as usize,
+                ));
+            }
+        }
+
+        Ok(())
     }
 
     fn is_mouse_down(&self) -> bool {
@@ -46,6 +89,10 @@ impl Input for GlutinInput {
         (self.mouse_x, self.mouse_y)
     }
 
+    fn get_resize(&self) -> Option<(usize, usize)> {
+        self.resize
+    }
+
     fn do_continue(&self) -> bool {
         self.running
     }
@@ -56,6 +103,7 @@ impl Input for GlutinInput {
             mouse_x: 0,
             mouse_y: 0,
             running: true,
+            resize: None,
         }
     }
 }
@@ -11,13 +11,16 @@ mod pi;
 pub use self::pi::PiInput as input_impl;
 
 use crate::render::Drawer;
+use crate::Error;
 
 /// Handles basic input
 pub trait Input {
     type Window: Drawer;
 
     /// Updates input
-    fn update(&mut self, drawer: &mut Self::Window);
+    ///
+    /// Fails if an input device couldn't be read.
+    fn update(&mut self, drawer: &mut Self::Window) -> Result<(), Error>;
 
     /// Checks to see if the mouse/pointer is down
     fn is_mouse_down(&self) -> bool;
@@ -25,6 +28,11 @@ pub trait Input {
     /// Returns the current mouse position in a (x, y) tuple.
     fn get_mouse_pos(&self) -> (usize, usize);
 
+    /// Returns the new size of the screen if it was resized since the previous update, in
+    /// the same units as `Drawer::get_width` and `Drawer::get_height`. The drawer (and its
+    /// viewport) picks up the new size at the start of the next frame.
+    fn get_resize(&self) -> Option<(usize, usize)>;
+
     /// Checks to see if execution should be continued
     fn do_continue(&self) -> bool;
 
@@ -2,24 +2,45 @@ use crate::input::Input;
 
 use crate::render::pi::drawer::PiDrawer;
 
+use crate::Error;
+
 use evdev;
 
+use std::io;
+
 /// Implements a basic input mechanism for the Pi through evdev.
 pub struct PiInput {
     devices: Vec<evdev::Device>,
     mouse_x: usize,
     mouse_y: usize,
     mouse_down: bool,
+    // The size of the display at the last update, and whether it changed then
+    size: Option<(usize, usize)>,
+    resize: Option<(usize, usize)>,
 }
 
 impl Input for PiInput {
     type Window = PiDrawer;
 
     /// Updates input
-    fn update(&mut self, _: &mut Self::Window) {
+    fn update(&mut self, drawer: &mut Self::Window) -> Result<(), Error> {
+        // The drawer only picks up a new resolution at the start of the next frame, so
+        // report it as soon as the display has it
+        let size = drawer.get_display_size();
+        self.resize = if self.size.unwrap_or_else(|| drawer.get_screen_size()) != size {
+            Some(size)
+        } else {
+            None
+        };
+        self.size = Some(size);
+
         let mut input = Vec::new();
         for device in &mut self.devices {
-            for evt in device.events_no_sync().unwrap() {
+            let events = device
+                .events_no_sync()
+                .map_err(|x| io::Error::new(io::ErrorKind::Other, x.to_string()))?;
+
+            for evt in events {
                 input.push(evt);
             }
         }
@@ -34,6 +55,8 @@ impl Input for PiInput {
         }
 
         self.mouse_down = touched;
+
+        Ok(())
     }
 
     /// Checks to see if the mouse/pointer is down
@@ -45,6 +68,10 @@ impl Input for PiInput {
         unimplemented!()
     }
 
+    fn get_resize(&self) -> Option<(usize, usize)> {
+        self.resize
+    }
+
     // No way of telling this
     fn do_continue(&self) -> bool {
         true
@@ -62,6 +89,8 @@ impl Input for PiInput {
             mouse_x: 0,
             mouse_y: 0,
             mouse_down: false,
+            size: None,
+            resize: None,
         }
     }
 }
@@ -11,13 +11,13 @@
 //! ## Basic usage
 //!
 //! Import the crate in your Cargo.toml with (assuming Windows/Linux OpenGL):
-//! ```
+//! ```toml
 //! leafrender = "0.1.0"
 //! ```
 //!
 //! In your code, have something like:
 //!
-//! ```
+//! ```no_run
 //! use leafrender::input::Input;
 //! use leafrender::render::Drawer;
 //! use leafrender::pos::Rect;
@@ -31,7 +31,7 @@
 //! let mut input = PlatformInput::new();
 //!
 //! while {
-//!    input.update(&mut drawer);
+//!    input.update(&mut drawer).expect("Failed to read input");
 //!        input.do_continue()
 //! } {
 //!     drawer.start();
@@ -54,7 +54,7 @@
 //!         }
 //!     );
 //!
-//!     drawer.end();
+//!     drawer.end().expect("Failed to present frame");
 //! }
 //! ```
 //!
@@ -62,7 +62,7 @@
 //!
 //! Want some text?
 //!
-//! ```
+//! ```ignore
 //! use crate::render::font::FontCache;
 //! use crate::pos::Position;
 //!
@@ -92,7 +92,7 @@
 //!
 //! Or an image?
 //!
-//! ```
+//! ```ignore
 //! use image; // image crate
 //!
 //! let image = image::load_from_memory(include_bytes!("img.jpg"))
@@ -113,14 +113,36 @@
 //! # Features
 //!
 //! - Drawing of rectangles, vertices w/ colors
+//! - Thick lines and polylines, with joins and caps
+//! - Circles, ellipses, arcs, pie slices and rounded rectangles, filled or outlined
+//! - Vector paths with Bézier curves, which can be filled or stroked
+//! - Linear and radial gradient fills, with any number of color stops
+//! - Translating, scaling and rotating draws with a transform stack
+//! - Clipping draws to nested rectangles
+//! - Drawing into textures (render targets), to cache or composite them later
+//! - Alpha, premultiplied, additive, multiply and screen blending
+//! - An optional premultiplied alpha pipeline, avoiding dark fringes on scaled images
+//! - Configurable texture filtering (nearest, linear, trilinear), wrapping and mipmaps
+//! - Partial texture updates and in-place resizing, for video and other live content
+//! - Compact texture formats (alpha, luminance, RGB, RGB565, RGBA4444) to save GPU memory
+//! - CPU-only rendering for machines without a GPU (`software` feature)
+//! - Headless (windowless) rendering, and reading back frames as images
+//! - Window options (fullscreen, borderless, resizable, vsync, MSAA, icons) via `DrawerConfig`
+//! - HiDPI awareness, with an option to lay out in logical units on every monitor
+//! - Automatic batching of draws into as few draw calls as possible
+//! - Golden-image snapshot testing of rendered frames (`leafrender::testing`)
 //! - Drawing of fonts using `rusttype`
 //! - Drawing of images using `image`
-//! - Basic input handling (mouse)
+//! - Packing of small images into a single texture atlas
+//! - Sprite sheets (from grids or TexturePacker/Aseprite JSON) and frame animation
+//! - Nine-slice drawing of textures, for frames and panels which stretch without distorting
+//! - Recording draws into display lists, directly or by wrapping any drawer in a
+//!   `Recorder`, which can be replayed or saved to disk
+//! - Basic input handling (mouse, window resizes)
 //!
 //! # Wishlist
 //!
 //! - More complete input handling (keyboard)
-//! - Clean up a few error handling edge cases
 //! - Few bits of safety could be cleaned up
 //!
 //! # Non-features
@@ -131,6 +153,7 @@
 
 extern crate image;
 extern crate rusttype;
+extern crate serde_json;
 
 extern crate libc;
 
@@ -148,12 +171,19 @@ extern crate gl;
 #[cfg(feature = "desktop_gl")]
 extern crate glutin;
 
+pub mod error;
+pub use crate::error::Error;
+
 pub mod pos;
 
 pub mod render;
 
+#[cfg(any(feature = "desktop_gl", feature = "raspberry_pi", feature = "software"))]
 pub use crate::render::drawer_impl as PlatformDrawer;
 
 pub mod input;
 
+pub mod testing;
+
+#[cfg(any(feature = "desktop_gl", feature = "raspberry_pi"))]
 pub use crate::input::input_impl as PlatformInput;
@@ -1,6 +1,7 @@
 //! Provides mechanisms for representing point/sections of the screen.
 
 /// Represents a X/Y position in pixels.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -13,6 +14,7 @@ impl Position {
 }
 
 /// Represents a X/Y position, width and height, all in pixels.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -38,4 +40,50 @@ impl Rect {
             height,
         }
     }
+
+    /// Returns the area covered by both this and another rectangle. If they don't
+    /// overlap, the returned rectangle has a width and/or height of 0.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let min_x = self.x.max(other.x);
+        let min_y = self.y.max(other.y);
+        let max_x = (self.x + self.width).min(other.x + other.width);
+        let max_y = (self.y + self.height).min(other.y + other.height);
+
+        Rect::new(min_x, min_y, (max_x - min_x).max(0), (max_y - min_y).max(0))
+    }
+
+    /// Returns the smallest rectangle which covers both this and another rectangle.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let min_x = self.x.min(other.x);
+        let min_y = self.y.min(other.y);
+        let max_x = (self.x + self.width).max(other.x + other.width);
+        let max_y = (self.y + self.height).max(other.y + other.height);
+
+        Rect::new(min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+}
+
+/// Represents distances in from each edge of a rectangle, in pixels.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Insets {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Insets {
+    pub fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Insets {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// Creates insets which are the same distance in from every edge.
+    pub fn uniform(size: i32) -> Self {
+        Insets::new(size, size, size, size)
+    }
 }
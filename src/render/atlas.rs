@@ -0,0 +1,407 @@
+//! Packs many small images into a single large texture, so that they can be drawn without
+//! switching textures (and therefore be batched together).
+
+use crate::pos::Rect;
+use crate::render::Dimensions;
+use crate::render::Drawer;
+use crate::render::PixelFormat;
+use crate::render::Texture;
+
+use image::RgbaImage;
+
+/// The gap left around each image, filled by extending its edges outwards so that linear
+/// filtering doesn't pull in neighbouring images.
+const PADDING: usize = 1;
+
+/// A handle to an image stored within a larger texture, such as a `TextureAtlas` or a
+/// `SpriteSheet`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct AtlasRegion {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    atlas_width: usize,
+    atlas_height: usize,
+}
+
+impl AtlasRegion {
+    /// Creates a region covering the specified pixels of a texture with the specified size.
+    pub(crate) fn new(
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        atlas_width: usize,
+        atlas_height: usize,
+    ) -> Self {
+        AtlasRegion {
+            x,
+            y,
+            width,
+            height,
+            atlas_width,
+            atlas_height,
+        }
+    }
+
+    /// Returns UV coordinates for this region, in the same vertex order as
+    /// `Drawer::rect_to_vertices`.
+    pub fn get_uv(&self) -> [f32; 12] {
+        let min_u = self.x as f32 / self.atlas_width as f32;
+        let max_u = (self.x + self.width) as f32 / self.atlas_width as f32;
+        let min_v = self.y as f32 / self.atlas_height as f32;
+        let max_v = (self.y + self.height) as f32 / self.atlas_height as f32;
+
+        [
+            min_u, min_v, min_u, max_v, max_u, max_v, // Triangle 1
+            min_u, min_v, max_u, min_v, max_u, max_v,
+        ]
+    }
+}
+
+impl Dimensions for AtlasRegion {
+    /// Returns the width of this region.
+    fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of this region.
+    fn get_height(&self) -> usize {
+        self.height
+    }
+}
+
+/// A horizontal strip of the atlas, which images are placed along from left to right.
+struct Shelf {
+    y: usize,
+    height: usize,
+    next_x: usize,
+}
+
+/// Packs RGBA images into one texture using a shelf packer. Images are kept on the CPU
+/// until the native texture is requested. After that, only the parts of the texture
+/// which have changed are uploaded again.
+pub struct TextureAtlas<T> {
+    pixels: Texture,
+    shelves: Vec<Shelf>,
+    native: Option<T>,
+    // The area which has changed since the native texture was last updated
+    dirty: Option<Rect>,
+}
+
+impl<T: Dimensions> TextureAtlas<T> {
+    /// Finds space for a image of the specified size (including padding), returning the
+    /// top-left corner of the space.
+    fn allocate(&mut self, width: usize, height: usize) -> Option<(usize, usize)> {
+        let atlas_width = self.pixels.get_width();
+        let atlas_height = self.pixels.get_height();
+
+        // Prefer the shelf which wastes the least height
+        let best_shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && shelf.next_x + width <= atlas_width)
+            .min_by_key(|shelf| shelf.height - height);
+
+        if let Some(shelf) = best_shelf {
+            let pos = (shelf.next_x, shelf.y);
+            shelf.next_x += width;
+            return Some(pos);
+        }
+
+        // Otherwise, open up a new shelf underneath the existing ones
+        let next_y = self
+            .shelves
+            .last()
+            .map(|shelf| shelf.y + shelf.height)
+            .unwrap_or(0);
+
+        if width > atlas_width || next_y + height > atlas_height {
+            return None;
+        }
+
+        self.shelves.push(Shelf {
+            y: next_y,
+            height,
+            next_x: width,
+        });
+
+        Some((0, next_y))
+    }
+
+    /// Copies a RGBA byte array into the atlas. Returns None if the array is too short for
+    /// the specified size.
+    fn insert_bytes(&mut self, bytes: &[u8], width: usize, height: usize) -> Option<AtlasRegion> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let length = width.checked_mul(height)?.checked_mul(4)?;
+        if bytes.len() < length {
+            return None;
+        }
+
+        let (x, y) = self.allocate(width + PADDING * 2, height + PADDING * 2)?;
+        let x = x + PADDING;
+        let y = y + PADDING;
+
+        let atlas_width = self.pixels.get_width();
+        let atlas_height = self.pixels.get_height();
+
+        // Copy the image, extending its edges into the padding around it
+        let padding = PADDING as isize;
+        for dest_y in -padding..(height as isize + padding) {
+            let src_y = dest_y.max(0).min(height as isize - 1) as usize;
+
+            for dest_x in -padding..(width as isize + padding) {
+                let src_x = dest_x.max(0).min(width as isize - 1) as usize;
+
+                let src = (src_y * width + src_x) * 4;
+                let dest = (((y as isize + dest_y) as usize) * atlas_width
+                    + (x as isize + dest_x) as usize)
+                    * 4;
+
+                self.pixels.tex_data[dest..dest + 4].copy_from_slice(&bytes[src..src + 4]);
+            }
+        }
+
+        let padded = Rect::new(
+            (x - PADDING) as i32,
+            (y - PADDING) as i32,
+            (width + PADDING * 2) as i32,
+            (height + PADDING * 2) as i32,
+        );
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(&padded),
+            None => padded,
+        });
+
+        Some(AtlasRegion::new(
+            x,
+            y,
+            width,
+            height,
+            atlas_width,
+            atlas_height,
+        ))
+    }
+
+    /// Inserts an image into the atlas. Returns None if the image is empty, or there isn't
+    /// enough space left.
+    pub fn insert_image(&mut self, image: &RgbaImage) -> Option<AtlasRegion> {
+        self.insert_bytes(
+            image.as_ref(),
+            image.width() as usize,
+            image.height() as usize,
+        )
+    }
+
+    /// Inserts a texture into the atlas, converting it to RGBA if needed. Returns None if
+    /// the texture is empty, or there isn't enough space left.
+    pub fn insert_texture(&mut self, texture: &Texture) -> Option<AtlasRegion> {
+        if texture.get_format() != PixelFormat::Rgba8888 {
+            return self.insert_texture(&texture.to_format(PixelFormat::Rgba8888));
+        }
+
+        self.insert_bytes(&texture.tex_data, texture.get_width(), texture.get_height())
+    }
+
+    /// Returns the native texture for this atlas, uploading any images which have been
+    /// inserted since it was last requested.
+    pub fn get_texture(&mut self, drawer: &mut dyn Drawer<NativeTexture = T>) -> &T {
+        match (self.native.as_mut(), self.dirty.take()) {
+            (None, _) => {
+                self.native = Some(drawer.convert_native_texture(self.pixels.clone()));
+            }
+            (Some(native), Some(dirty)) => {
+                let atlas_width = self.pixels.get_width();
+                let start = dirty.x as usize * 4;
+                let end = (dirty.x + dirty.width) as usize * 4;
+
+                let mut bytes =
+                    Vec::with_capacity(dirty.width as usize * dirty.height as usize * 4);
+                for y in dirty.y as usize..(dirty.y + dirty.height) as usize {
+                    let row = y * atlas_width * 4;
+                    bytes.extend_from_slice(&self.pixels.tex_data[row + start..row + end]);
+                }
+
+                drawer
+                    .update_texture(native, &dirty, &bytes)
+                    .expect("Dirty area should lie within the atlas");
+            }
+            (Some(_), None) => {}
+        }
+
+        self.native.as_ref().unwrap()
+    }
+
+    /// Removes all images from the atlas. Existing regions must no longer be used.
+    pub fn clear(&mut self) {
+        let width = self.pixels.get_width();
+        let height = self.pixels.get_height();
+
+        self.pixels = Texture::new(width, height);
+        self.shelves.clear();
+        self.dirty = Some(Rect::new(0, 0, width as i32, height as i32));
+    }
+
+    /// Creates a new, empty atlas of the specified size.
+    pub fn new(width: usize, height: usize) -> Self {
+        TextureAtlas {
+            pixels: Texture::new(width, height),
+            shelves: Vec::new(),
+            native: None,
+            dirty: None,
+        }
+    }
+}
+
+impl<T> Dimensions for TextureAtlas<T> {
+    /// Returns the width of this atlas.
+    fn get_width(&self) -> usize {
+        self.pixels.get_width()
+    }
+
+    /// Returns the height of this atlas.
+    fn get_height(&self) -> usize {
+        self.pixels.get_height()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::render::Color;
+
+    // The native texture is never requested, so any type with a size will do
+    type Atlas = TextureAtlas<AtlasRegion>;
+
+    /// Checks if two regions overlap, including the padding around them.
+    fn overlaps(a: &AtlasRegion, b: &AtlasRegion) -> bool {
+        a.x < b.x + b.width + PADDING * 2
+            && b.x < a.x + a.width + PADDING * 2
+            && a.y < b.y + b.height + PADDING * 2
+            && b.y < a.y + a.height + PADDING * 2
+    }
+
+    #[test]
+    fn packs_images_without_overlapping() {
+        let mut atlas = Atlas::new(64, 64);
+
+        let mut regions = Vec::new();
+        for size in [
+            (10, 6),
+            (4, 12),
+            (20, 6),
+            (7, 7),
+            (30, 3),
+            (5, 12),
+            (12, 12),
+        ]
+        .iter()
+        {
+            let (width, height) = *size;
+            let bytes = vec![255; width * height * 4];
+            regions.push(atlas.insert_bytes(&bytes, width, height).unwrap());
+        }
+
+        for (i, a) in regions.iter().enumerate() {
+            assert!(a.x >= PADDING && a.x + a.width + PADDING <= 64);
+            assert!(a.y >= PADDING && a.y + a.height + PADDING <= 64);
+
+            for b in &regions[i + 1..] {
+                assert!(!overlaps(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn reuses_shelves_which_fit() {
+        let mut atlas = Atlas::new(64, 64);
+
+        let tall = atlas.insert_bytes(&[0; 10 * 10 * 4], 10, 10).unwrap();
+        let short = atlas.insert_bytes(&[0; 10 * 4 * 4], 10, 4).unwrap();
+        assert_eq!(short.y, tall.y);
+        assert_eq!(short.x, tall.x + tall.width + PADDING * 2);
+
+        // Too tall for the first shelf, so a new one is opened underneath it
+        let taller = atlas.insert_bytes(&[0; 10 * 20 * 4], 10, 20).unwrap();
+        assert_eq!(taller.x, PADDING);
+        assert_eq!(taller.y, tall.y + tall.height + PADDING * 2);
+    }
+
+    #[test]
+    fn pads_images_with_their_edges() {
+        let mut atlas = Atlas::new(8, 8);
+
+        let bytes = [
+            255, 0, 0, 255, 0, 255, 0, 255, // Top row
+            0, 0, 255, 255, 255, 255, 255, 255,
+        ];
+        let region = atlas.insert_bytes(&bytes, 2, 2).unwrap();
+        let (x, y) = (region.x, region.y);
+
+        assert_eq!(
+            atlas.pixels.get_pixel(x - 1, y - 1),
+            Color::new_3byte(255, 0, 0)
+        );
+        assert_eq!(
+            atlas.pixels.get_pixel(x + 2, y),
+            Color::new_3byte(0, 255, 0)
+        );
+        assert_eq!(
+            atlas.pixels.get_pixel(x - 1, y + 2),
+            Color::new_3byte(0, 0, 255)
+        );
+        assert_eq!(
+            atlas.pixels.get_pixel(x + 2, y + 2),
+            Color::new_3byte(255, 255, 255)
+        );
+    }
+
+    #[test]
+    fn rejects_images_which_dont_fit() {
+        let mut atlas = Atlas::new(16, 16);
+
+        assert!(atlas.insert_bytes(&[0; 15 * 4 * 4], 15, 4).is_none());
+        assert!(atlas.insert_bytes(&[], 0, 0).is_none());
+
+        // Fill the atlas up, then check that it reports when it is full
+        for _ in 0..4 {
+            assert!(atlas.insert_bytes(&[0; 14 * 2 * 4], 14, 2).is_some());
+        }
+        assert!(atlas.insert_bytes(&[0; 14 * 2 * 4], 14, 2).is_none());
+
+        atlas.clear();
+        assert!(atlas.insert_bytes(&[0; 14 * 2 * 4], 14, 2).is_some());
+    }
+
+    #[test]
+    fn converts_textures_to_rgba() {
+        let mut atlas = Atlas::new(16, 16);
+
+        let mut texture = Texture::new_with_format(2, 2, PixelFormat::Rgb565);
+        texture.draw_pixel(&Color::new_3byte(255, 0, 0), 1, 1);
+
+        let region = atlas.insert_texture(&texture).unwrap();
+        assert_eq!(
+            atlas.pixels.get_pixel(region.x + 1, region.y + 1),
+            Color::new_3byte(255, 0, 0)
+        );
+        assert_eq!(
+            atlas.pixels.get_pixel(region.x, region.y),
+            Color::new_3byte(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn rejects_short_images() {
+        let mut atlas = Atlas::new(16, 16);
+
+        assert!(atlas.insert_bytes(&[0; 15], 2, 2).is_none());
+        assert!(atlas.insert_bytes(&[0; 16], 2, 2).is_some());
+        assert!(atlas.insert_bytes(&[0; 16], usize::MAX, 2).is_none());
+    }
+}
@@ -0,0 +1,135 @@
+//! Gathers consecutive draws into a single set of vertex arrays, so that they can be
+//! submitted to the GPU with one draw call.
+
+/// The vertex data of draws which haven't been submitted yet. Triangle strips are unrolled
+/// into separate triangles, so that unrelated draws can share the same buffers.
+pub struct Batch {
+    pub vertices: Vec<f32>,
+    pub colors: Vec<f32>,
+    pub uv: Vec<f32>,
+    /// Flips vertices vertically as they are pushed. Render targets are stored bottom row
+    /// first, so this keeps them the same way up as other textures.
+    pub flip_y: bool,
+}
+
+impl Batch {
+    /// Returns the number of vertices in this batch.
+    pub fn len(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    /// Checks if there is anything to draw.
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Empties this batch, keeping allocations around for the next one.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.colors.clear();
+        self.uv.clear();
+    }
+
+    /// Appends a triangle strip to this batch. The UV array may be empty for untextured
+    /// draws.
+    pub fn push_strip(&mut self, vertices: &[f32], colors: &[f32], uv: &[f32]) {
+        let count = (vertices.len() / 2).min(colors.len() / 4);
+
+        for i in 2..count {
+            let triangle = [i - 2, i - 1, i];
+
+            // Skip degenerate triangles, which are commonly used to stitch strips together
+            let (ax, ay) = (vertices[(i - 2) * 2], vertices[(i - 2) * 2 + 1]);
+            let (bx, by) = (vertices[(i - 1) * 2], vertices[(i - 1) * 2 + 1]);
+            let (cx, cy) = (vertices[i * 2], vertices[i * 2 + 1]);
+            if (bx - ax) * (cy - ay) - (by - ay) * (cx - ax) == 0.0 {
+                continue;
+            }
+
+            for &index in &triangle {
+                let y = vertices[index * 2 + 1];
+                self.vertices.push(vertices[index * 2]);
+                self.vertices.push(if self.flip_y { -y } else { y });
+                self.colors
+                    .extend_from_slice(&colors[index * 4..index * 4 + 4]);
+
+                if uv.len() >= index * 2 + 2 {
+                    self.uv.extend_from_slice(&uv[index * 2..index * 2 + 2]);
+                } else if !uv.is_empty() {
+                    self.uv.extend_from_slice(&[0.0, 0.0]);
+                }
+            }
+        }
+    }
+
+    /// Creates a new, empty batch.
+    pub fn new() -> Self {
+        Batch {
+            vertices: Vec::new(),
+            colors: Vec::new(),
+            uv: Vec::new(),
+            flip_y: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+    fn colors(count: usize) -> Vec<f32> {
+        WHITE.iter().cycle().take(count * 4).cloned().collect()
+    }
+
+    #[test]
+    fn unrolls_strips_into_triangles() {
+        let mut batch = Batch::new();
+        let quad = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        batch.push_strip(&quad, &colors(4), &[]);
+
+        assert_eq!(batch.len(), 6);
+        assert_eq!(
+            batch.vertices,
+            vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0]
+        );
+        assert_eq!(batch.colors, colors(6));
+        assert!(batch.uv.is_empty());
+
+        batch.clear();
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn skips_degenerate_triangles() {
+        let mut batch = Batch::new();
+        // Two quads stitched together by repeating the last vertex of the first and the
+        // first vertex of the second
+        let strips = [
+            0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, 3.0, 2.0, 2.0,
+            3.0, 3.0, 3.0,
+        ];
+        batch.push_strip(&strips, &colors(10), &[]);
+
+        assert_eq!(batch.len(), 12);
+        assert_eq!(&batch.vertices[12..18], &[2.0, 2.0, 3.0, 2.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn flips_vertices_vertically() {
+        let mut batch = Batch::new();
+        batch.flip_y = true;
+        batch.push_strip(&[0.0, 0.5, 1.0, 0.5, 0.0, -1.0], &colors(3), &[]);
+
+        assert_eq!(batch.vertices, vec![0.0, -0.5, 1.0, -0.5, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn pads_missing_uvs() {
+        let mut batch = Batch::new();
+        batch.push_strip(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0], &colors(3), &[0.25, 0.75]);
+
+        assert_eq!(batch.uv, vec![0.25, 0.75, 0.0, 0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,42 @@
+//! Tracks the area of the screen which draws are restricted to.
+
+use crate::pos::Rect;
+
+/// A stack of clip rectangles, in screen pixels, each intersected with the ones
+/// underneath it.
+pub struct ClipStack {
+    stack: Vec<Rect>,
+}
+
+impl ClipStack {
+    /// Returns the area which draws are currently restricted to, if any.
+    pub fn get(&self) -> Option<Rect> {
+        self.stack.last().cloned()
+    }
+
+    /// Intersects the specified rectangle with the current clip, and makes it current.
+    pub fn push(&mut self, rect: &Rect) {
+        let clip = match self.get() {
+            Some(current) => current.intersection(rect),
+            None => *rect,
+        };
+
+        self.stack.push(clip);
+    }
+
+    /// Restores the clip which was current before the last push. Does nothing if the
+    /// stack is empty.
+    pub fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Removes all clips, allowing draws to cover the whole screen.
+    pub fn clear(&mut self) {
+        self.stack.clear();
+    }
+
+    /// Creates a new, empty stack.
+    pub fn new() -> Self {
+        ClipStack { stack: Vec::new() }
+    }
+}
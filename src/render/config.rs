@@ -0,0 +1,127 @@
+//! Describes the window (or screen) which a drawer should be created with.
+
+use image::RgbaImage;
+
+/// Options for creating a drawer, passed to `Drawer::with_config`.
+///
+/// Not every platform supports every option. Options which a drawer couldn't honour are
+/// listed by `Drawer::config_warnings` once it has been created, rather than failing.
+///
+/// ```rust
+/// use leafrender::render::config::DrawerConfig;
+///
+/// // A borderless fullscreen kiosk
+/// let kiosk = DrawerConfig::new("Kiosk", 1920, 1080)
+///     .with_fullscreen(true)
+///     .with_decorations(false)
+///     .with_samples(4);
+///
+/// // A regular desktop tool, laid out the same on HiDPI monitors
+/// let tool = DrawerConfig::new("Tool", 800, 600)
+///     .with_min_size(400, 300)
+///     .with_logical_units(true);
+/// ```
+#[derive(Clone)]
+pub struct DrawerConfig {
+    pub(crate) title: String,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) fullscreen: bool,
+    pub(crate) resizable: bool,
+    pub(crate) decorations: bool,
+    pub(crate) vsync: bool,
+    pub(crate) samples: u16,
+    pub(crate) icon: Option<RgbaImage>,
+    pub(crate) min_size: Option<(u32, u32)>,
+    pub(crate) logical_units: bool,
+}
+
+impl DrawerConfig {
+    /// Creates a config for a resizable, decorated window with vsync and without
+    /// multisampling, which is drawn to in physical pixels.
+    ///
+    /// The size is in logical units, so that the window appears the same size on monitors
+    /// with different scale factors.
+    pub fn new(title: &str, width: u32, height: u32) -> Self {
+        DrawerConfig {
+            title: title.to_string(),
+            width,
+            height,
+            fullscreen: false,
+            resizable: true,
+            decorations: true,
+            vsync: true,
+            samples: 0,
+            icon: None,
+            min_size: None,
+            logical_units: false,
+        }
+    }
+
+    /// Sets if the window should cover the whole of the primary monitor.
+    pub fn with_fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    /// Sets if the user can resize the window.
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Sets if the window has a title bar and borders. Combine with fullscreen for a
+    /// borderless fullscreen window.
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    /// Sets if frames should wait for the display to refresh before being shown.
+    pub fn with_vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    /// Sets how many samples each pixel is multisampled (MSAA) with, smoothing the edges
+    /// of shapes. 0 disables multisampling. Other counts are rounded up to a power of two.
+    pub fn with_samples(mut self, samples: u16) -> Self {
+        self.samples = if samples == 0 {
+            0
+        } else {
+            samples.next_power_of_two()
+        };
+        self
+    }
+
+    /// Sets the icon shown for the window, such as in the taskbar.
+    pub fn with_icon(mut self, icon: RgbaImage) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Sets the smallest size which the user can resize the window to.
+    pub fn with_min_size(mut self, width: u32, height: u32) -> Self {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    /// Sets if draws, clips, `get_width`/`get_height` and mouse positions are in logical
+    /// units rather than physical pixels. Logical units are scaled by the monitor's scale
+    /// factor (see `Drawer::scale_factor`), so the same layout appears the same size on every
+    /// monitor. Render targets and captures are always in pixels.
+    pub fn with_logical_units(mut self, logical_units: bool) -> Self {
+        self.logical_units = logical_units;
+        self
+    }
+
+    /// Returns the title of the window.
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    /// Returns the requested size of the window.
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
@@ -0,0 +1,1130 @@
+//! Records drawing calls into a value, which can be replayed against any drawer, and
+//! saved to (or loaded from) disk.
+//!
+//! Textures aren't stored in the list itself - instead, draws refer to textures by an ID
+//! chosen by the application, which are looked up again when the list is replayed. Text
+//! is stored as a string, and laid out again with a font when replayed.
+//!
+//! ## Format
+//!
+//! Lists are serialized to a compact, little-endian binary format:
+//!
+//! - The magic bytes `LRDL`, followed by the format version as a `u32` (currently `1`).
+//! - The number of commands as a `u32`, followed by each command.
+//! - Each command is a `u8` tag, followed by its fields in declaration order. Rects and
+//!   positions are stored as `i32`s, colors as 4 `u8`s, booleans as a `u8`, blend modes
+//!   as a `u8` (in declaration order), transforms as 6 `f32`s (`xx`, `xy`, `yx`, `yy`,
+//!   `x0`, `y0`), and strings and float arrays as a `u32` length followed by their UTF-8
+//!   bytes or `f32`s.
+//!
+//! New commands may be added with new tags without changing the version. Changes to
+//! existing commands will increase the version.
+//!
+//! ## Recording
+//!
+//! Lists can be built up by hand, or recorded by wrapping a drawer in a `Recorder`. The
+//! recorder is itself a drawer, which passes every call through to the drawer it wraps, and
+//! records the raw vertices that shapes and textures are turned into, along with changes to
+//! the transform, clip and blend mode. Text is recorded as a string when drawn with
+//! `Recorder::draw_text`.
+//!
+//! Render targets aren't recorded. Clears and draws made while a target is set are passed
+//! through, but left out of the list, so that lists only ever describe what was drawn to
+//! the screen. Changes to the transform, clip and blend mode are still recorded, as they
+//! carry on once the target is unset.
+
+use crate::pos::Position;
+use crate::pos::Rect;
+
+use crate::render::config::DrawerConfig;
+use crate::render::font::FontCache;
+use crate::render::target::RenderTarget;
+use crate::render::transform::Transform;
+use crate::render::BlendMode;
+use crate::render::Color;
+use crate::render::Dimensions;
+use crate::render::Drawer;
+use crate::render::Texture;
+use crate::render::TextureOptions;
+use crate::render::UniqueTexture;
+use crate::Error;
+
+use image::DynamicImage;
+use image::RgbaImage;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Identifies the start of a serialized display list.
+const MAGIC: &[u8; 4] = b"LRDL";
+
+/// The version of the serialized format that is written by this version of the library.
+pub const FORMAT_VERSION: u32 = 1;
+
+const TAG_CLEAR: u8 = 0;
+const TAG_ENABLE_BLENDING: u8 = 1;
+const TAG_COLORED_VERTICES: u8 = 2;
+const TAG_TEXTURED_VERTICES: u8 = 3;
+const TAG_COLORED_RECT: u8 = 4;
+const TAG_TEXTURE: u8 = 5;
+const TAG_TEXT: u8 = 6;
+const TAG_PUSH_TRANSFORM: u8 = 7;
+const TAG_POP_TRANSFORM: u8 = 8;
+const TAG_PUSH_CLIP: u8 = 9;
+const TAG_POP_CLIP: u8 = 10;
+const TAG_SET_BLEND_MODE: u8 = 11;
+
+/// A single recorded drawing call.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Clears the frame. See `Drawer::clear`.
+    Clear { transparent: bool },
+    /// Enables blending. See `Drawer::enable_blending`.
+    EnableBlending,
+    /// Draws colored vertices, in OpenGL coordinates. See `Drawer::draw_colored_vertices`.
+    ColoredVertices {
+        vertices: Vec<f32>,
+        colors: Vec<f32>,
+    },
+    /// Draws a textured set of vertices, in OpenGL coordinates. See
+    /// `Drawer::draw_textured_vertices_colored_uv`.
+    TexturedVertices {
+        texture: u32,
+        vertices: Vec<f32>,
+        colors: Vec<f32>,
+        uv: Vec<f32>,
+    },
+    /// Draws a single colored rectangle, in pixels. See `Drawer::draw_colored_rect`.
+    ColoredRect { rect: Rect, color: Color },
+    /// Draws a texture into a rectangle, in pixels. See `Drawer::draw_texture_sized`.
+    Texture {
+        texture: u32,
+        rect: Rect,
+        color: Color,
+    },
+    /// Draws a string of text. See `FontCache::draw`.
+    Text {
+        text: String,
+        color: Color,
+        size: i32,
+        pos: Position,
+    },
+    /// Pushes a transform. See `Drawer::push_transform`.
+    PushTransform { transform: Transform },
+    /// Pops the last transform. See `Drawer::pop_transform`.
+    PopTransform,
+    /// Pushes a clip rectangle, in pixels. See `Drawer::push_clip`.
+    PushClip { rect: Rect },
+    /// Pops the last clip rectangle. See `Drawer::pop_clip`.
+    PopClip,
+    /// Changes the blend mode. See `Drawer::set_blend_mode`.
+    SetBlendMode { mode: BlendMode },
+}
+
+/// A recorded sequence of drawing calls.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DisplayList {
+    commands: Vec<Command>,
+}
+
+impl DisplayList {
+    /// Records a clear of the frame.
+    pub fn clear(&mut self, transparent: bool) {
+        self.commands.push(Command::Clear { transparent });
+    }
+
+    /// Records blending being enabled.
+    pub fn enable_blending(&mut self) {
+        self.commands.push(Command::EnableBlending);
+    }
+
+    /// Records a set of colored vertices being drawn.
+    pub fn draw_colored_vertices(&mut self, vertices: &[f32], colors: &[f32]) {
+        self.commands.push(Command::ColoredVertices {
+            vertices: vertices.to_vec(),
+            colors: colors.to_vec(),
+        });
+    }
+
+    /// Records a texture being drawn with a set of vertices, UV and colors.
+    pub fn draw_textured_vertices_colored_uv(
+        &mut self,
+        texture: u32,
+        vertices: &[f32],
+        colors: &[f32],
+        uv: &[f32],
+    ) {
+        self.commands.push(Command::TexturedVertices {
+            texture,
+            vertices: vertices.to_vec(),
+            colors: colors.to_vec(),
+            uv: uv.to_vec(),
+        });
+    }
+
+    /// Records a colored rectangle being drawn.
+    pub fn draw_colored_rect(&mut self, rect: &Rect, color: &Color) {
+        self.commands.push(Command::ColoredRect {
+            rect: *rect,
+            color: color.clone(),
+        });
+    }
+
+    /// Records a texture being drawn into a rectangle.
+    pub fn draw_texture_sized(&mut self, texture: u32, rect: &Rect, color: &Color) {
+        self.commands.push(Command::Texture {
+            texture,
+            rect: *rect,
+            color: color.clone(),
+        });
+    }
+
+    /// Records a string of text being drawn.
+    pub fn draw_text(&mut self, text: &str, color: &Color, size: i32, pos: &Position) {
+        self.commands.push(Command::Text {
+            text: text.to_string(),
+            color: color.clone(),
+            size,
+            pos: *pos,
+        });
+    }
+
+    /// Records a transform being pushed.
+    pub fn push_transform(&mut self, transform: &Transform) {
+        self.commands.push(Command::PushTransform {
+            transform: *transform,
+        });
+    }
+
+    /// Records the last transform being popped.
+    pub fn pop_transform(&mut self) {
+        self.commands.push(Command::PopTransform);
+    }
+
+    /// Records a clip rectangle being pushed.
+    pub fn push_clip(&mut self, rect: &Rect) {
+        self.commands.push(Command::PushClip { rect: *rect });
+    }
+
+    /// Records the last clip rectangle being popped.
+    pub fn pop_clip(&mut self) {
+        self.commands.push(Command::PopClip);
+    }
+
+    /// Records the blend mode being changed.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.commands.push(Command::SetBlendMode { mode });
+    }
+
+    /// Appends an already built command to this list.
+    pub fn push(&mut self, command: Command) {
+        self.commands.push(command);
+    }
+
+    /// Returns the commands recorded so far.
+    pub fn get_commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Returns the number of recorded commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Checks if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Removes all recorded commands.
+    pub fn reset(&mut self) {
+        self.commands.clear();
+    }
+
+    /// Replays this list against a drawer. Texture IDs are looked up in the specified
+    /// map, and text is drawn with the specified font.
+    ///
+    /// Fails if a texture is missing, or text is recorded but no font was given. Commands
+    /// before the failing one will have already been drawn.
+    pub fn replay<T: Dimensions>(
+        &self,
+        drawer: &mut dyn Drawer<NativeTexture = T>,
+        textures: &BTreeMap<u32, T>,
+        mut font: Option<&mut FontCache<T>>,
+    ) -> Result<(), Error> {
+        let get_texture = |id: &u32| {
+            textures.get(id).ok_or_else(|| {
+                Error::InvalidData(format!("Display list refers to missing texture {}", id))
+            })
+        };
+
+        for command in &self.commands {
+            match command {
+                Command::Clear { transparent } => drawer.clear(*transparent),
+                Command::EnableBlending => drawer.enable_blending(),
+                Command::ColoredVertices { vertices, colors } => {
+                    drawer.draw_colored_vertices(vertices, colors)
+                }
+                Command::TexturedVertices {
+                    texture,
+                    vertices,
+                    colors,
+                    uv,
+                } => drawer.draw_textured_vertices_colored_uv(
+                    get_texture(texture)?,
+                    vertices,
+                    colors,
+                    uv,
+                ),
+                Command::ColoredRect { rect, color } => drawer.draw_colored_rect(rect, color),
+                Command::Texture {
+                    texture,
+                    rect,
+                    color,
+                } => drawer.draw_texture_sized(get_texture(texture)?, rect, color),
+                Command::Text {
+                    text,
+                    color,
+                    size,
+                    pos,
+                } => match font {
+                    Some(ref mut font) => font.draw(text, color, *size, pos, drawer),
+                    None => {
+                        return Err(Error::InvalidData(
+                            "Display list contains text, but no font was given".into(),
+                        ))
+                    }
+                },
+                Command::PushTransform { transform } => drawer.push_transform(transform),
+                Command::PopTransform => drawer.pop_transform(),
+                Command::PushClip { rect } => drawer.push_clip(rect),
+                Command::PopClip => drawer.pop_clip(),
+                Command::SetBlendMode { mode } => drawer.set_blend_mode(*mode),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serializes this list into the binary format described in the module documentation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer { data: Vec::new() };
+
+        writer.data.extend_from_slice(MAGIC);
+        writer.write_u32(FORMAT_VERSION);
+        writer.write_u32(self.commands.len() as u32);
+
+        for command in &self.commands {
+            match command {
+                Command::Clear { transparent } => {
+                    writer.write_u8(TAG_CLEAR);
+                    writer.write_u8(*transparent as u8);
+                }
+                Command::EnableBlending => {
+                    writer.write_u8(TAG_ENABLE_BLENDING);
+                }
+                Command::ColoredVertices { vertices, colors } => {
+                    writer.write_u8(TAG_COLORED_VERTICES);
+                    writer.write_floats(vertices);
+                    writer.write_floats(colors);
+                }
+                Command::TexturedVertices {
+                    texture,
+                    vertices,
+                    colors,
+                    uv,
+                } => {
+                    writer.write_u8(TAG_TEXTURED_VERTICES);
+                    writer.write_u32(*texture);
+                    writer.write_floats(vertices);
+                    writer.write_floats(colors);
+                    writer.write_floats(uv);
+                }
+                Command::ColoredRect { rect, color } => {
+                    writer.write_u8(TAG_COLORED_RECT);
+                    writer.write_rect(rect);
+                    writer.write_color(color);
+                }
+                Command::Texture {
+                    texture,
+                    rect,
+                    color,
+                } => {
+                    writer.write_u8(TAG_TEXTURE);
+                    writer.write_u32(*texture);
+                    writer.write_rect(rect);
+                    writer.write_color(color);
+                }
+                Command::Text {
+                    text,
+                    color,
+                    size,
+                    pos,
+                } => {
+                    writer.write_u8(TAG_TEXT);
+                    writer.write_string(text);
+                    writer.write_color(color);
+                    writer.write_i32(*size);
+                    writer.write_i32(pos.x);
+                    writer.write_i32(pos.y);
+                }
+                Command::PushTransform { transform } => {
+                    writer.write_u8(TAG_PUSH_TRANSFORM);
+                    writer.write_transform(transform);
+                }
+                Command::PopTransform => {
+                    writer.write_u8(TAG_POP_TRANSFORM);
+                }
+                Command::PushClip { rect } => {
+                    writer.write_u8(TAG_PUSH_CLIP);
+                    writer.write_rect(rect);
+                }
+                Command::PopClip => {
+                    writer.write_u8(TAG_POP_CLIP);
+                }
+                Command::SetBlendMode { mode } => {
+                    writer.write_u8(TAG_SET_BLEND_MODE);
+                    writer.write_blend_mode(*mode);
+                }
+            }
+        }
+
+        writer.data
+    }
+
+    /// Reads a list from the binary format described in the module documentation.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { data, offset: 0 };
+
+        if reader.read_bytes(MAGIC.len())? != MAGIC {
+            return Err(Error::InvalidData("Not a display list (bad magic)".into()));
+        }
+
+        let version = reader.read_u32()?;
+        if version != FORMAT_VERSION {
+            return Err(Error::InvalidData(format!(
+                "Unsupported display list version {} (expected {})",
+                version, FORMAT_VERSION
+            )));
+        }
+
+        let count = reader.read_u32()? as usize;
+        let mut commands = Vec::new();
+
+        for _ in 0..count {
+            let tag = reader.read_u8()?;
+
+            let command = match tag {
+                TAG_CLEAR => Command::Clear {
+                    transparent: reader.read_u8()? != 0,
+                },
+                TAG_ENABLE_BLENDING => Command::EnableBlending,
+                TAG_COLORED_VERTICES => Command::ColoredVertices {
+                    vertices: reader.read_floats()?,
+                    colors: reader.read_floats()?,
+                },
+                TAG_TEXTURED_VERTICES => Command::TexturedVertices {
+                    texture: reader.read_u32()?,
+                    vertices: reader.read_floats()?,
+                    colors: reader.read_floats()?,
+                    uv: reader.read_floats()?,
+                },
+                TAG_COLORED_RECT => Command::ColoredRect {
+                    rect: reader.read_rect()?,
+                    color: reader.read_color()?,
+                },
+                TAG_TEXTURE => Command::Texture {
+                    texture: reader.read_u32()?,
+                    rect: reader.read_rect()?,
+                    color: reader.read_color()?,
+                },
+                TAG_TEXT => Command::Text {
+                    text: reader.read_string()?,
+                    color: reader.read_color()?,
+                    size: reader.read_i32()?,
+                    pos: Position::new(reader.read_i32()?, reader.read_i32()?),
+                },
+                TAG_PUSH_TRANSFORM => Command::PushTransform {
+                    transform: reader.read_transform()?,
+                },
+                TAG_POP_TRANSFORM => Command::PopTransform,
+                TAG_PUSH_CLIP => Command::PushClip {
+                    rect: reader.read_rect()?,
+                },
+                TAG_POP_CLIP => Command::PopClip,
+                TAG_SET_BLEND_MODE => Command::SetBlendMode {
+                    mode: reader.read_blend_mode()?,
+                },
+                _ => {
+                    return Err(Error::InvalidData(format!(
+                        "Unknown display list command {}",
+                        tag
+                    )))
+                }
+            };
+
+            commands.push(command);
+        }
+
+        if reader.offset != data.len() {
+            return Err(Error::InvalidData(
+                "Trailing data after display list".into(),
+            ));
+        }
+
+        Ok(DisplayList { commands })
+    }
+
+    /// Writes this list to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Reads a list from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let data = fs::read(path)?;
+        DisplayList::from_bytes(&data)
+    }
+
+    /// Creates a new, empty list.
+    pub fn new() -> Self {
+        DisplayList {
+            commands: Vec::new(),
+        }
+    }
+}
+
+/// Wraps a drawer, recording the calls made to it into a `DisplayList` as they are passed
+/// through. Replaying the list against the same drawer (or another of the same size) draws
+/// the same frame again.
+///
+/// Draws are recorded as the vertices they are turned into, which are already in OpenGL
+/// coordinates. Textures are recorded using the IDs given to `register_texture`; draws
+/// using textures which haven't been registered are drawn, but not recorded. This includes
+/// text drawn by passing the recorder to `FontCache::draw`, so text should be drawn with
+/// `draw_text` instead. Draws into render targets aren't recorded.
+pub struct Recorder<D: Drawer> {
+    drawer: D,
+    list: DisplayList,
+    // Set while draws are going into a render target, rather than the screen
+    target_bound: bool,
+    // Registered textures, by their unique ID
+    texture_ids: BTreeMap<u64, u32>,
+}
+
+impl<D: Drawer> Recorder<D>
+where
+    D::NativeTexture: UniqueTexture,
+{
+    /// Starts recording the calls made to the specified drawer.
+    pub fn new(drawer: D) -> Self {
+        Recorder {
+            drawer,
+            list: DisplayList::new(),
+            target_bound: false,
+            texture_ids: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the ID that draws using the specified texture are recorded with, which should
+    /// be the ID it is given when the list is replayed.
+    pub fn register_texture(&mut self, texture: &D::NativeTexture, id: u32) {
+        self.texture_ids.insert(texture.get_unique_id(), id);
+    }
+
+    /// Draws a string of text with the specified font, recording the string itself so that
+    /// it is laid out again with the font given when the list is replayed.
+    pub fn draw_text(
+        &mut self,
+        font: &mut FontCache<D::NativeTexture>,
+        text: &str,
+        color: &Color,
+        size: i32,
+        pos: &Position,
+    ) {
+        if !self.target_bound {
+            self.list.draw_text(text, color, size, pos);
+        }
+        font.draw(text, color, size, pos, &mut self.drawer);
+    }
+
+    /// Forgets all registered textures.
+    pub fn clear_textures(&mut self) {
+        self.texture_ids.clear();
+    }
+
+    /// Returns what has been recorded so far.
+    pub fn get_list(&self) -> &DisplayList {
+        &self.list
+    }
+
+    /// Returns what has been recorded so far, and starts a new, empty list.
+    pub fn take_list(&mut self) -> DisplayList {
+        std::mem::take(&mut self.list)
+    }
+
+    /// Returns the drawer which calls are passed through to.
+    pub fn get_drawer(&mut self) -> &mut D {
+        &mut self.drawer
+    }
+
+    /// Stops recording, returning the wrapped drawer.
+    pub fn into_inner(self) -> D {
+        self.drawer
+    }
+}
+
+impl<D: Drawer> Drawer for Recorder<D>
+where
+    D::NativeTexture: UniqueTexture,
+{
+    type NativeTexture = D::NativeTexture;
+
+    fn start(&mut self) {
+        self.drawer.start()
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        // Targets are unset at the end of the frame
+        self.target_bound = false;
+        self.drawer.end()
+    }
+
+    fn clear(&mut self, transparent: bool) {
+        if !self.target_bound {
+            self.list.clear(transparent);
+        }
+        self.drawer.clear(transparent)
+    }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.list.set_blend_mode(mode);
+        self.drawer.set_blend_mode(mode)
+    }
+
+    fn get_blend_mode(&self) -> BlendMode {
+        self.drawer.get_blend_mode()
+    }
+
+    fn set_premultiplied_alpha(&mut self, enabled: bool) {
+        self.drawer.set_premultiplied_alpha(enabled)
+    }
+
+    fn is_premultiplied_alpha(&self) -> bool {
+        self.drawer.is_premultiplied_alpha()
+    }
+
+    fn flush(&mut self) {
+        self.drawer.flush()
+    }
+
+    fn convert_image_with_options(
+        &mut self,
+        texture: &RgbaImage,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture {
+        self.drawer.convert_image_with_options(texture, options)
+    }
+
+    fn convert_native_texture_with_options(
+        &mut self,
+        texture: Texture,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture {
+        self.drawer
+            .convert_native_texture_with_options(texture, options)
+    }
+
+    fn update_texture(
+        &mut self,
+        texture: &mut Self::NativeTexture,
+        region: &Rect,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.drawer.update_texture(texture, region, data)
+    }
+
+    fn resize_texture(&mut self, texture: &mut Self::NativeTexture, width: usize, height: usize) {
+        self.drawer.resize_texture(texture, width, height)
+    }
+
+    fn get_width(&self) -> usize {
+        self.drawer.get_width()
+    }
+
+    fn get_height(&self) -> usize {
+        self.drawer.get_height()
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.drawer.scale_factor()
+    }
+
+    fn uses_logical_units(&self) -> bool {
+        self.drawer.uses_logical_units()
+    }
+
+    fn set_background(&mut self, image: DynamicImage) {
+        self.drawer.set_background(image)
+    }
+
+    fn set_brightness(&mut self, brightness: u8) -> Result<(), Error> {
+        self.drawer.set_brightness(brightness)
+    }
+
+    fn draw_textured_vertices_colored_uv(
+        &mut self,
+        texture: &Self::NativeTexture,
+        vertices: &[f32],
+        colors: &[f32],
+        uv: &[f32],
+    ) {
+        if let Some(&id) = self.texture_ids.get(&texture.get_unique_id()) {
+            if !self.target_bound {
+                self.list
+                    .draw_textured_vertices_colored_uv(id, vertices, colors, uv);
+            }
+        }
+
+        self.drawer
+            .draw_textured_vertices_colored_uv(texture, vertices, colors, uv)
+    }
+
+    fn draw_colored_vertices(&mut self, vertices: &[f32], colors: &[f32]) {
+        if !self.target_bound {
+            self.list.draw_colored_vertices(vertices, colors);
+        }
+        self.drawer.draw_colored_vertices(vertices, colors)
+    }
+
+    fn get_transition_count(&self) -> usize {
+        self.drawer.get_transition_count()
+    }
+
+    fn push_transform(&mut self, transform: &Transform) {
+        self.list.push_transform(transform);
+        self.drawer.push_transform(transform)
+    }
+
+    fn pop_transform(&mut self) {
+        self.list.pop_transform();
+        self.drawer.pop_transform()
+    }
+
+    fn get_transform(&self) -> Transform {
+        self.drawer.get_transform()
+    }
+
+    fn push_clip(&mut self, rect: &Rect) {
+        self.list.push_clip(rect);
+        self.drawer.push_clip(rect)
+    }
+
+    fn pop_clip(&mut self) {
+        self.list.pop_clip();
+        self.drawer.pop_clip()
+    }
+
+    fn get_clip(&self) -> Option<Rect> {
+        self.drawer.get_clip()
+    }
+
+    fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderTarget<Self::NativeTexture>, Error> {
+        self.drawer.create_render_target(width, height)
+    }
+
+    fn set_render_target(&mut self, target: Option<&RenderTarget<Self::NativeTexture>>) {
+        self.target_bound = target.is_some();
+        self.drawer.set_render_target(target)
+    }
+
+    fn capture(&mut self) -> RgbaImage {
+        self.drawer.capture()
+    }
+
+    fn with_config(config: DrawerConfig) -> Result<Self, Error> {
+        D::with_config(config).map(Recorder::new)
+    }
+
+    fn config_warnings(&self) -> &[String] {
+        self.drawer.config_warnings()
+    }
+
+    fn new_headless(width: u32, height: u32) -> Result<Self, Error> {
+        D::new_headless(width, height).map(Recorder::new)
+    }
+}
+
+/// Appends little-endian values to a byte array.
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_floats(&mut self, values: &[f32]) {
+        self.write_u32(values.len() as u32);
+        for value in values {
+            self.write_f32(*value);
+        }
+    }
+
+    fn write_string(&mut self, value: &str) {
+        self.write_u32(value.len() as u32);
+        self.data.extend_from_slice(value.as_bytes());
+    }
+
+    fn write_rect(&mut self, rect: &Rect) {
+        self.write_i32(rect.x);
+        self.write_i32(rect.y);
+        self.write_i32(rect.width);
+        self.write_i32(rect.height);
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.data.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+
+    fn write_transform(&mut self, transform: &Transform) {
+        self.write_f32(transform.xx);
+        self.write_f32(transform.xy);
+        self.write_f32(transform.yx);
+        self.write_f32(transform.yy);
+        self.write_f32(transform.x0);
+        self.write_f32(transform.y0);
+    }
+
+    fn write_blend_mode(&mut self, mode: BlendMode) {
+        self.write_u8(match mode {
+            BlendMode::None => 0,
+            BlendMode::Alpha => 1,
+            BlendMode::PremultipliedAlpha => 2,
+            BlendMode::Additive => 3,
+            BlendMode::Multiply => 4,
+            BlendMode::Screen => 5,
+        });
+    }
+
+    fn write_color(&mut self, color: &Color) {
+        self.data
+            .extend_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+}
+
+/// Reads little-endian values from a byte array, failing if it runs out.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() - self.offset < len {
+            return Err(Error::InvalidData("Unexpected end of display list".into()));
+        }
+
+        let bytes = &self.data[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Error> {
+        Ok(self.read_u32()? as i32)
+    }
+
+    fn read_floats(&mut self) -> Result<Vec<f32>, Error> {
+        let len = self.read_u32()? as usize;
+        let bytes = self.read_bytes(len.saturating_mul(4))?;
+
+        Ok(bytes
+            .chunks(4)
+            .map(|x| f32::from_bits(u32::from_le_bytes([x[0], x[1], x[2], x[3]])))
+            .collect())
+    }
+
+    fn read_string(&mut self) -> Result<String, Error> {
+        let len = self.read_u32()? as usize;
+        let bytes = self.read_bytes(len)?;
+
+        String::from_utf8(bytes.to_vec())
+            .map_err(|x| Error::InvalidData(format!("Invalid text in display list: {}", x)))
+    }
+
+    fn read_rect(&mut self) -> Result<Rect, Error> {
+        Ok(Rect::new(
+            self.read_i32()?,
+            self.read_i32()?,
+            self.read_i32()?,
+            self.read_i32()?,
+        ))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_bits(self.read_u32()?))
+    }
+
+    fn read_transform(&mut self) -> Result<Transform, Error> {
+        Ok(Transform {
+            xx: self.read_f32()?,
+            xy: self.read_f32()?,
+            yx: self.read_f32()?,
+            yy: self.read_f32()?,
+            x0: self.read_f32()?,
+            y0: self.read_f32()?,
+        })
+    }
+
+    fn read_blend_mode(&mut self) -> Result<BlendMode, Error> {
+        match self.read_u8()? {
+            0 => Ok(BlendMode::None),
+            1 => Ok(BlendMode::Alpha),
+            2 => Ok(BlendMode::PremultipliedAlpha),
+            3 => Ok(BlendMode::Additive),
+            4 => Ok(BlendMode::Multiply),
+            5 => Ok(BlendMode::Screen),
+            x => Err(Error::InvalidData(format!("Unknown blend mode {}", x))),
+        }
+    }
+
+    fn read_color(&mut self) -> Result<Color, Error> {
+        let bytes = self.read_bytes(4)?;
+        Ok(Color::new_4byte(bytes[0], bytes[1], bytes[2], bytes[3]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::render::software::drawer::SoftwareDrawer;
+
+    fn checkerboard() -> Texture {
+        let mut texture = Texture::new(2, 2);
+        texture.draw_pixel(&Color::new_3byte(255, 255, 255), 0, 0);
+        texture.draw_pixel(&Color::new_4byte(0, 0, 255, 128), 1, 0);
+        texture.draw_pixel(&Color::new_4byte(0, 0, 255, 128), 0, 1);
+        texture.draw_pixel(&Color::new_3byte(255, 255, 255), 1, 1);
+        texture
+    }
+
+    #[test]
+    fn replays_recorded_frames() {
+        let mut recorder = Recorder::<SoftwareDrawer>::new_headless(64, 64).unwrap();
+        recorder.start();
+
+        let texture = recorder.convert_native_texture(checkerboard());
+        let unregistered = recorder.convert_native_texture(checkerboard());
+        recorder.register_texture(&texture, 7);
+
+        recorder.clear(false);
+        recorder.draw_colored_rect(&Rect::new(0, 0, 64, 16), &Color::new_3byte(255, 0, 0));
+
+        recorder.set_blend_mode(BlendMode::Alpha);
+        recorder.push_transform(&Transform::translate(8.0, 4.0));
+        recorder.push_clip(&Rect::new(0, 0, 40, 40));
+        recorder.draw_texture_sized(
+            &texture,
+            &Rect::new(0, 0, 48, 48),
+            &Color::new_3byte(255, 255, 255),
+        );
+        recorder.draw_circle(
+            &Position::new(32, 32),
+            12.0,
+            &Color::new_4byte(0, 255, 0, 128),
+        );
+        recorder.pop_clip();
+        recorder.pop_transform();
+        recorder.draw_texture_sized(
+            &unregistered,
+            &Rect::new(48, 48, 16, 16),
+            &Color::new_3byte(255, 255, 255),
+        );
+
+        let expected = recorder.capture();
+        recorder.end().unwrap();
+
+        // The unregistered texture is drawn, but left out of the list
+        let list = recorder.take_list();
+        assert!(recorder.get_list().is_empty());
+        assert_eq!(
+            list.get_commands()
+                .iter()
+                .filter(|command| match command {
+                    Command::TexturedVertices { texture, .. } => *texture == 7,
+                    _ => false,
+                })
+                .count(),
+            1
+        );
+        assert!(list.get_commands().contains(&Command::PushClip {
+            rect: Rect::new(0, 0, 40, 40)
+        }));
+        assert!(list.get_commands().contains(&Command::SetBlendMode {
+            mode: BlendMode::Alpha
+        }));
+
+        // Lists survive being saved, and draw the same frame when replayed
+        let list = DisplayList::from_bytes(&list.to_bytes()).unwrap();
+
+        let mut drawer = SoftwareDrawer::new_headless(64, 64).unwrap();
+        drawer.start();
+
+        let mut textures = BTreeMap::new();
+        textures.insert(7, drawer.convert_native_texture(checkerboard()));
+        list.replay(&mut drawer, &textures, None).unwrap();
+
+        let mut replayed = drawer.capture();
+        drawer.end().unwrap();
+
+        // Apart from the draw which wasn't recorded
+        let unrecorded = expected.get_pixel(56, 56);
+        assert_ne!(replayed.get_pixel(56, 56), unrecorded);
+        for y in 48..64 {
+            for x in 48..64 {
+                replayed.put_pixel(x, y, *expected.get_pixel(x, y));
+            }
+        }
+
+        assert!(expected.into_raw() == replayed.into_raw());
+    }
+
+    #[test]
+    fn replays_recorded_text() {
+        let font_data = include_bytes!("../../examples/kitchensink/res/Lato-Regular.ttf");
+        let white = Color::new_3byte(255, 255, 255);
+
+        let mut recorder = Recorder::<SoftwareDrawer>::new_headless(64, 32).unwrap();
+        recorder.start();
+        recorder.clear(false);
+        recorder.set_blend_mode(BlendMode::Alpha);
+
+        let mut font = FontCache::from_bytes(font_data).unwrap();
+        recorder.draw_text(&mut font, "Hello", &white, 20, &Position::new(4, 20));
+
+        let expected = recorder.capture();
+        recorder.end().unwrap();
+
+        assert!(expected.pixels().any(|pixel| pixel[0] > 128));
+
+        // Glyphs aren't recorded as textures, only the text itself
+        let list = recorder.take_list();
+        assert_eq!(
+            list.get_commands().last(),
+            Some(&Command::Text {
+                text: "Hello".into(),
+                color: white,
+                size: 20,
+                pos: Position::new(4, 20),
+            })
+        );
+        assert!(!list
+            .get_commands()
+            .iter()
+            .any(|command| matches!(command, Command::TexturedVertices { .. })));
+
+        let list = DisplayList::from_bytes(&list.to_bytes()).unwrap();
+
+        let mut drawer = SoftwareDrawer::new_headless(64, 32).unwrap();
+        drawer.start();
+
+        let mut font = FontCache::from_bytes(font_data).unwrap();
+        list.replay(&mut drawer, &BTreeMap::new(), Some(&mut font))
+            .unwrap();
+
+        let replayed = drawer.capture();
+        drawer.end().unwrap();
+
+        assert!(expected.into_raw() == replayed.into_raw());
+    }
+
+    #[test]
+    fn recognises_registered_textures_after_moving_them() {
+        let mut recorder = Recorder::<SoftwareDrawer>::new_headless(16, 16).unwrap();
+        recorder.start();
+
+        let texture = recorder.convert_native_texture(checkerboard());
+        recorder.register_texture(&texture, 3);
+
+        let textures = [texture];
+        recorder.draw_texture(&textures[0], &Position::new(0, 0));
+        recorder.end().unwrap();
+
+        assert!(recorder
+            .get_list()
+            .get_commands()
+            .iter()
+            .any(|command| match command {
+                Command::TexturedVertices { texture, .. } => *texture == 3,
+                _ => false,
+            }));
+    }
+
+    #[test]
+    fn leaves_out_draws_into_render_targets() {
+        let mut recorder = Recorder::<SoftwareDrawer>::new_headless(32, 32).unwrap();
+        recorder.start();
+        recorder.clear(false);
+        recorder.draw_colored_rect(&Rect::new(0, 0, 16, 16), &Color::new_3byte(255, 0, 0));
+
+        let target = recorder.create_render_target(8, 8).unwrap();
+        recorder.set_render_target(Some(&target));
+        recorder.clear(true);
+        recorder.push_transform(&Transform::translate(2.0, 2.0));
+        recorder.draw_colored_rect(&Rect::new(0, 0, 8, 8), &Color::new_3byte(0, 255, 0));
+        recorder.set_render_target(None);
+
+        recorder.draw_colored_rect(&Rect::new(0, 0, 8, 8), &Color::new_3byte(0, 0, 255));
+        recorder.pop_transform();
+
+        let expected = recorder.capture();
+        recorder.end().unwrap();
+
+        let list = recorder.take_list();
+        assert_eq!(
+            list.get_commands()
+                .iter()
+                .filter(|command| matches!(command, Command::ColoredVertices { .. }))
+                .count(),
+            2
+        );
+        assert!(list.get_commands().contains(&Command::PushTransform {
+            transform: Transform::translate(2.0, 2.0)
+        }));
+
+        let mut drawer = SoftwareDrawer::new_headless(32, 32).unwrap();
+        drawer.start();
+        list.replay(&mut drawer, &BTreeMap::new(), None).unwrap();
+
+        let replayed = drawer.capture();
+        drawer.end().unwrap();
+
+        assert!(expected.into_raw() == replayed.into_raw());
+    }
+}
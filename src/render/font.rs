@@ -6,18 +6,24 @@ use rusttype::Point;
 use rusttype::Scale;
 
 use crate::pos::Position;
+use crate::pos::Rect;
 
+use crate::render::transform::Transform;
 use crate::render::Color;
 use crate::render::Dimensions;
 use crate::render::Drawer;
+use crate::render::PixelFormat;
 use crate::render::Texture;
 
+use crate::Error;
+
 use std::collections::BTreeMap;
 
+// Glyphs are cached as white coverage, and tinted when they are drawn, so the same glyph
+// can be reused for any color
 #[derive(Clone, Hash, Ord, PartialOrd, Eq, PartialEq)]
 struct CachedGlyph {
     id: GlyphId,
-    color: Color,
     size: i32,
 }
 
@@ -54,17 +60,28 @@ impl<'a, T: Dimensions> FontCache<'a, T> {
         color: &Color,
         size: i32,
         pos: &Position,
-        draw: &mut Drawer<NativeTexture = T>,
+        draw: &mut dyn Drawer<NativeTexture = T>,
     ) {
+        // Glyphs are rendered at physical size, so that text drawn in logical units stays
+        // sharp on HiDPI monitors
+        let scale = if draw.uses_logical_units() {
+            draw.scale_factor() as f32
+        } else {
+            1.0
+        };
+        let size = (size as f32 * scale).round() as i32;
+
         let layout = self.font.layout(
             text,
             Scale::uniform(size as f32),
             Point {
-                x: pos.x as f32,
-                y: pos.y as f32,
+                x: pos.x as f32 * scale,
+                y: pos.y as f32 * scale,
             },
         );
 
+        draw.push_transform(&Transform::scale(1.0 / scale, 1.0 / scale));
+
         for glyph in layout {
             // Render out texture
             let bounding_box_opt = glyph.pixel_bounding_box();
@@ -78,20 +95,20 @@ impl<'a, T: Dimensions> FontCache<'a, T> {
             // Build hash ID for this glyph
             let id = CachedGlyph {
                 id: glyph.id(),
-                color: color.clone(),
                 size,
             };
 
             if !self.cache.contains_key(&id) {
-                let mut tex = Texture::new(
+                let mut tex = Texture::new_with_format(
                     bounding_box.width() as usize,
                     bounding_box.height() as usize,
+                    PixelFormat::Alpha8,
                 );
 
                 {
                     let render_pos = |x: u32, y: u32, factor: f32| {
                         tex.draw_pixel(
-                            &color.alpha((factor * 255.0) as u8),
+                            &Color::new_4byte(255, 255, 255, (factor * 255.0) as u8),
                             x as usize,
                             y as usize,
                         );
@@ -109,22 +126,30 @@ impl<'a, T: Dimensions> FontCache<'a, T> {
             let tex = &self.cache[&id];
 
             // Setup vertice data
-            draw.draw_texture(
+            draw.draw_texture_sized(
                 tex,
-                &Position::new(bounding_box.min.x as i32, bounding_box.min.y as i32),
+                &Rect::new(
+                    bounding_box.min.x,
+                    bounding_box.min.y,
+                    bounding_box.width(),
+                    bounding_box.height(),
+                ),
+                color,
             );
         }
+
+        draw.pop_transform();
     }
 
     /// Creates a new cache from a .ttf file.
-    pub fn from_bytes(data: &'a [u8]) -> Result<Self, String> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, Error> {
         let collection =
-            FontCollection::from_bytes(data).map_err(|x| format!("Failed to read font: {}", x))?;
+            FontCollection::from_bytes(data).map_err(|x| Error::Font(x.to_string()))?;
 
         // only succeeds if collection consists of one font
         let font = collection
             .into_font()
-            .map_err(|x| format!("Failed to read font: {}", x))?;
+            .map_err(|x| Error::Font(x.to_string()))?;
 
         Ok(FontCache {
             font,
@@ -2,27 +2,42 @@
 
 use crate::render::Drawer;
 use crate::render::Texture;
+use crate::render::TextureOptions;
 
+use image::imageops;
 use image::DynamicImage;
 use image::RgbaImage;
 
+use crate::render::batch::Batch;
+use crate::render::clip::ClipStack;
+use crate::render::config::DrawerConfig;
+use crate::render::glutin::framebuffer::GLFramebuffer;
 use crate::render::glutin::texture::GlTexture;
+use crate::render::glutin::texture::ReleaseQueue;
+use crate::render::transform::Transform;
+use crate::render::transform::TransformStack;
 
 use crate::render::glutin::shader::GLSLShader;
 
 use crate::render::glutin::vbo::GLVBO;
 
+use crate::render::check_texture_update;
+use crate::render::target::RenderTarget;
+use crate::render::BlendMode;
+use crate::render::Dimensions;
+
 use crate::pos::Rect;
-use crate::render::Color;
+use crate::Error;
 
 use glutin;
 use glutin::dpi::LogicalSize;
+use glutin::dpi::PhysicalSize;
+use glutin::Context;
 use glutin::PossiblyCurrent;
 use glutin::WindowedContext;
 
 use gl;
 
-use std::mem;
 use std::ptr;
 
 use std::os::raw::c_char;
@@ -35,9 +50,30 @@ enum DrawState {
     Textured,
 }
 
+/// A render target which draws are currently redirected into.
+struct BoundTarget {
+    framebuffer: gl::types::GLuint,
+    width: usize,
+    height: usize,
+}
+
+/// The OpenGL context which is being drawn into.
+pub(crate) enum GlutinContext {
+    /// A context attached to a visible window.
+    Windowed(WindowedContext<PossiblyCurrent>),
+    /// A context without a window, drawing into a framebuffer object instead.
+    Headless {
+        framebuffer: GLFramebuffer,
+        texture: GlTexture,
+        // Only held to keep the context alive; dropped last
+        #[allow(dead_code)]
+        context: Context<PossiblyCurrent>,
+    },
+}
+
 pub struct GlutinDrawer {
     pub events_loop: glutin::EventsLoop,
-    pub gl_window: WindowedContext<PossiblyCurrent>,
+    pub(crate) context: GlutinContext,
 
     colored: GLSLShader,
     textured: GLSLShader,
@@ -56,22 +92,180 @@ pub struct GlutinDrawer {
     uv: GLVBO,
     attr_textured_uv: gl::types::GLint,
 
+    // Set to whether draws are premultiplied, and whether the bound texture is
+    uniform_colored_premultiply: gl::types::GLint,
+    uniform_textured_premultiply: gl::types::GLint,
+    uniform_textured_premultiplied_tex: gl::types::GLint,
+
     // Background image
     background: Option<GlTexture>,
 
     state: DrawState,
 
+    // Draws waiting to be submitted, and the texture they use
+    batch: Batch,
+    batch_texture: Option<gl::types::GLuint>,
+    batch_premultiplied: bool,
+    // Textures dropped while the batch may still have been using them
+    releases: ReleaseQueue,
+
+    premultiplied_alpha: bool,
+
+    transforms: TransformStack,
+    clips: ClipStack,
+    blend_mode: BlendMode,
+    target: Option<BoundTarget>,
+
     // Debugging
     transition_count: usize,
     calls: usize,
+    config_warnings: Vec<String>,
+
+    // Set if draws are in logical units, rather than physical pixels
+    pub(crate) logical_units: bool,
+
+    // The size and scale factor of the window, as of the start of this frame
+    window_size: (u32, u32),
+    window_scale: f64,
 }
 
 impl GlutinDrawer {
+    /// Returns the window being drawn to, if this drawer isn't headless.
+    pub fn get_window(&self) -> Option<&WindowedContext<PossiblyCurrent>> {
+        match self.context {
+            GlutinContext::Windowed(ref window) => Some(window),
+            GlutinContext::Headless { .. } => None,
+        }
+    }
+
+    /// Returns the size of the surface being drawn to, in physical pixels.
+    fn get_size(&self) -> (u32, u32) {
+        if let Some(ref target) = self.target {
+            return (target.width as u32, target.height as u32);
+        }
+
+        match self.context {
+            GlutinContext::Windowed(_) => self.window_size,
+            GlutinContext::Headless { ref texture, .. } => {
+                (texture.get_width() as u32, texture.get_height() as u32)
+            }
+        }
+    }
+
+    /// Reads the current size and scale factor of the window, so that they stay the same
+    /// for the rest of the frame even if the window is resized.
+    fn update_window_size(&mut self) {
+        if let GlutinContext::Windowed(ref window) = self.context {
+            let window = window.window();
+            self.window_scale = window.get_hidpi_factor();
+            // Windows which have been closed have no size left to draw into
+            self.window_size = window
+                .get_inner_size()
+                .map(|size| size.to_physical(self.window_scale).into())
+                .unwrap_or((0, 0));
+        }
+    }
+
+    /// Returns how many physical pixels make up one unit which is being drawn in.
+    fn units_scale(&self) -> f64 {
+        if self.logical_units && self.target.is_none() {
+            self.scale_factor()
+        } else {
+            1.0
+        }
+    }
+
+    /// Finishes setting up a drawer, once the OpenGL context is current and loaded.
+    fn from_context(
+        events_loop: glutin::EventsLoop,
+        context: GlutinContext,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, Error> {
+        unsafe {
+            gl::DebugMessageCallback(gl_debug_message, ptr::null_mut());
+
+            gl::ClearColor(0.0, 1.0, 0.0, 1.0);
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+
+        let vertex_vbo = GLVBO::new();
+        let color_vbo = GLVBO::new();
+        let uv_vbo = GLVBO::new();
+
+        unsafe {
+            let mut ptr = 0;
+            gl::GenVertexArrays(1, &mut ptr);
+            gl::BindVertexArray(ptr);
+
+            // Textures with 1 or 3 bytes per pixel don't have rows aligned to 4 bytes
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        }
+
+        let colored_shader = GLSLShader::create_shader(
+            include_bytes!("../../../res/glutin_shaders/color.vert"),
+            include_bytes!("../../../res/glutin_shaders/color.frag"),
+        )?;
+
+        colored_shader.use_program();
+        let attr_colored_vertex = colored_shader.get_attribute("input_vertex");
+        let attr_colored_color = colored_shader.get_attribute("input_color");
+        let uniform_colored_premultiply = colored_shader.get_uniform("premultiply");
+
+        let textured_shader = GLSLShader::create_shader(
+            include_bytes!("../../../res/glutin_shaders/tex.vert"),
+            include_bytes!("../../../res/glutin_shaders/tex.frag"),
+        )?;
+
+        textured_shader.use_program();
+        let attr_textured_vertex = textured_shader.get_attribute("input_vertex");
+        let attr_textured_color = textured_shader.get_attribute("input_color");
+        let attr_textured_uv = textured_shader.get_attribute("input_uv");
+        let uniform_textured_premultiply = textured_shader.get_uniform("premultiply");
+        let uniform_textured_premultiplied_tex = textured_shader.get_uniform("premultiplied_tex");
+
+        Ok(GlutinDrawer {
+            events_loop,
+            context,
+            colored: colored_shader,
+            textured: textured_shader,
+            state: DrawState::None,
+            vertex: vertex_vbo,
+            attr_colored_vertex,
+            attr_textured_vertex,
+            color: color_vbo,
+            attr_colored_color,
+            attr_textured_color,
+            uv: uv_vbo,
+            attr_textured_uv,
+            uniform_colored_premultiply,
+            uniform_textured_premultiply,
+            uniform_textured_premultiplied_tex,
+            background: None,
+            batch: Batch::new(),
+            batch_texture: None,
+            batch_premultiplied: false,
+            releases: ReleaseQueue::default(),
+            premultiplied_alpha: false,
+            transforms: TransformStack::new(),
+            clips: ClipStack::new(),
+            blend_mode: BlendMode::None,
+            target: None,
+            transition_count: 0,
+            calls: 0,
+            config_warnings: Vec::new(),
+            logical_units: false,
+            window_size: (width, height),
+            window_scale: 1.0,
+        })
+    }
+
     /// Changes shaders, and ensures that G is ready to use it.
     fn configure_state(&mut self, target: DrawState) {
         self.calls += 1;
 
         if self.state != target {
+            self.flush();
             self.transition_count += 1;
 
             // Unbind previous state, if needed
@@ -168,6 +362,72 @@ impl GlutinDrawer {
             self.state = target;
         }
     }
+
+    /// Updates the scissor test to match the current clip.
+    fn apply_clip(&mut self) {
+        // Anything batched was drawn under the previous clip
+        self.flush();
+
+        match self.clips.get() {
+            Some(rect) => unsafe {
+                let scale = self.units_scale() as f32;
+                let rect = Transform::scale(scale, scale).bounding_rect(&rect);
+
+                // OpenGL's origin is at the bottom left, but render targets are flipped
+                let y = if self.target.is_some() {
+                    rect.y
+                } else {
+                    self.get_size().1 as i32 - (rect.y + rect.height)
+                };
+
+                gl::Enable(gl::SCISSOR_TEST);
+                gl::Scissor(rect.x, y, rect.width, rect.height);
+            },
+            None => unsafe { gl::Disable(gl::SCISSOR_TEST) },
+        }
+    }
+
+    /// Binds the framebuffer which draws should go into, and sizes the viewport to match.
+    fn bind_output(&mut self) {
+        // Anything batched was drawn into the previous output
+        self.flush();
+
+        match self.target {
+            Some(ref target) => unsafe {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer);
+            },
+            None => match self.context {
+                GlutinContext::Windowed(_) => GLFramebuffer::unbind(),
+                GlutinContext::Headless {
+                    ref framebuffer, ..
+                } => framebuffer.bind(),
+            },
+        }
+
+        self.batch.flip_y = self.target.is_some();
+
+        let (width, height) = self.get_size();
+
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+
+        self.apply_clip();
+    }
+
+    /// Deletes textures which were dropped while the batch may still have been using them.
+    fn delete_released_textures(&mut self) {
+        let released = match self.releases.lock() {
+            Ok(mut releases) => std::mem::take(&mut *releases),
+            Err(_) => return,
+        };
+
+        if !released.is_empty() {
+            unsafe {
+                gl::DeleteTextures(released.len() as _, released.as_ptr());
+            }
+        }
+    }
 }
 
 impl Drawer for GlutinDrawer {
@@ -176,94 +436,183 @@ impl Drawer for GlutinDrawer {
     fn start(&mut self) {
         self.calls = 0;
         self.transition_count = 0;
+        self.update_window_size();
 
         self.state = DrawState::None;
+        self.transforms.clear();
+        self.clips.clear();
 
-        let (width, height): (u32, u32) = self
-            .gl_window
-            .window()
-            .get_inner_size()
-            .expect("Failed to get size of current window")
-            .into();
+        self.target = None;
+        self.bind_output();
 
-        unsafe {
-            gl::Viewport(0, 0, width as i32, height as i32);
-        }
+        self.set_blend_mode(BlendMode::None);
     }
 
     /// Ends this frame.
-    fn end(&mut self) {
+    fn end(&mut self) -> Result<(), Error> {
+        self.set_render_target(None);
+        self.set_blend_mode(BlendMode::None);
         self.configure_state(DrawState::None);
-
-        self.gl_window.swap_buffers().unwrap();
+        self.batch_texture = None;
+
+        match self.context {
+            GlutinContext::Windowed(ref window) => window
+                .swap_buffers()
+                .map_err(|x| Error::Context(format!("Failed to swap buffers: {}", x))),
+            GlutinContext::Headless { .. } => {
+                unsafe { gl::Flush() };
+                Ok(())
+            }
+        }
     }
 
     /// Clears the framebuffer.
     fn clear(&mut self, transparent: bool) {
+        self.flush();
+
+        let alpha = if transparent { 0.0 } else { 1.0 };
+
         unsafe {
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::ClearColor(0.0, 0.0, 0.0, alpha);
             gl::Clear(gl::COLOR_BUFFER_BIT);
         }
 
         // Draw our background here, if required
-        if transparent && self.background.is_some() {
-            let size = Rect::new(0, 0, self.get_width() as i32, self.get_height() as i32);
-            let tex = self.background.take();
-            let tex = tex.unwrap();
-            self.draw_texture_sized(&tex, &size, &Color::new_3byte(255, 255, 255));
+        if transparent && self.target.is_none() && self.background.is_some() {
+            let tex = self.background.take().unwrap();
+            // Cover the whole screen, regardless of the current transform
+            self.draw_textured_vertices(
+                &tex,
+                &[
+                    -1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0, 1.0, -1.0,
+                ],
+            );
             self.background = Some(tex);
         }
     }
 
-    /// Enables blending of alpha textures. Disabled at end of frame.
-    fn enable_blending(&mut self) {
+    /// Changes the blending function. Reset at end of frame.
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.flush();
+        self.blend_mode = mode;
+
+        // Colors are already multiplied by alpha in the premultiplied pipeline
+        let (source, dest) = match (mode, self.premultiplied_alpha) {
+            (BlendMode::None, _) => {
+                unsafe { gl::Disable(gl::BLEND) };
+                return;
+            }
+            (BlendMode::Alpha, false) => (gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+            (BlendMode::Alpha, true) | (BlendMode::PremultipliedAlpha, _) => {
+                (gl::ONE, gl::ONE_MINUS_SRC_ALPHA)
+            }
+            (BlendMode::Additive, false) => (gl::SRC_ALPHA, gl::ONE),
+            (BlendMode::Additive, true) => (gl::ONE, gl::ONE),
+            (BlendMode::Multiply, false) => (gl::DST_COLOR, gl::ZERO),
+            (BlendMode::Multiply, true) => (gl::DST_COLOR, gl::ONE_MINUS_SRC_ALPHA),
+            (BlendMode::Screen, _) => (gl::ONE, gl::ONE_MINUS_SRC_COLOR),
+        };
+
         unsafe {
             gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::BlendFunc(source, dest);
         }
     }
 
-    fn convert_image(&mut self, texture: &RgbaImage) -> Self::NativeTexture {
-        GlTexture::from_image(&texture)
+    fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Switches the premultiplied alpha pipeline on or off.
+    fn set_premultiplied_alpha(&mut self, enabled: bool) {
+        self.flush();
+        self.premultiplied_alpha = enabled;
+
+        // Blending factors depend on the pipeline
+        let mode = self.blend_mode;
+        self.set_blend_mode(mode);
+    }
+
+    fn is_premultiplied_alpha(&self) -> bool {
+        self.premultiplied_alpha
+    }
+
+    fn convert_image_with_options(
+        &mut self,
+        texture: &RgbaImage,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture {
+        GlTexture::from_image(texture, self.premultiplied_alpha, options)
+            .with_release_queue(&self.releases)
+    }
+
+    fn convert_native_texture_with_options(
+        &mut self,
+        texture: Texture,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture {
+        GlTexture::from_texture(&texture, self.premultiplied_alpha, options)
+            .with_release_queue(&self.releases)
+    }
+
+    fn update_texture(
+        &mut self,
+        texture: &mut Self::NativeTexture,
+        region: &Rect,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        check_texture_update(
+            texture.get_width(),
+            texture.get_height(),
+            texture.get_format(),
+            region,
+            data,
+        )?;
+
+        // Draws waiting in the batch must see the old contents
+        self.flush();
+        texture.update(region, data);
+        Ok(())
     }
 
-    fn convert_native_texture(&mut self, texture: Texture) -> Self::NativeTexture {
-        GlTexture::from_texture(&texture)
+    fn resize_texture(&mut self, texture: &mut Self::NativeTexture, width: usize, height: usize) {
+        self.flush();
+        texture.resize(width, height);
     }
 
     /// Returns the width of the screen.
     fn get_width(&self) -> usize {
-        let (width, _): (u32, u32) = self
-            .gl_window
-            .window()
-            .get_inner_size()
-            .expect("Failed to get size of current window")
-            .into();
+        let (width, _) = self.get_size();
 
-        width as usize
+        (f64::from(width) / self.units_scale()).round() as usize
     }
 
     /// Returns the height of the screen.
     fn get_height(&self) -> usize {
-        let (_, height): (u32, u32) = self
-            .gl_window
-            .window()
-            .get_inner_size()
-            .expect("Failed to get size of current window")
-            .into();
+        let (_, height) = self.get_size();
+
+        (f64::from(height) / self.units_scale()).round() as usize
+    }
 
-        height as usize
+    /// Returns the scale factor of the monitor which the window is on.
+    fn scale_factor(&self) -> f64 {
+        self.window_scale
+    }
+
+    fn uses_logical_units(&self) -> bool {
+        self.logical_units
     }
 
     /// Uses the specified image as a background. This is provided as several platforms
     /// have ways to accelerate this beyond OpenGL calls.
     fn set_background(&mut self, image: DynamicImage) {
-        let image = GlTexture::from_image(&image.to_rgba());
+        let image = GlTexture::from_image(&image.to_rgba(), false, &TextureOptions::default())
+            .with_release_queue(&self.releases);
         self.background = Some(image);
     }
 
     /// Sets the brightness of the screen.
-    fn set_brightness(&mut self, _val: u8) -> ::std::io::Result<()> {
+    fn set_brightness(&mut self, _val: u8) -> Result<(), Error> {
         // NOOP
         Ok(())
     }
@@ -277,124 +626,283 @@ impl Drawer for GlutinDrawer {
         colors: &[f32],
         uv: &[f32],
     ) {
-        self.configure_state(DrawState::Textured);
-
-        self.color.set_data(colors);
-        self.vertex.set_data(vertices);
-        self.uv.set_data(uv);
+        if self.batch_texture != Some(texture.get_id()) {
+            self.flush();
+        }
 
-        texture.bind_texture(gl::TEXTURE_2D);
+        self.configure_state(DrawState::Textured);
 
-        unsafe {
-            gl::DrawArrays(
-                gl::TRIANGLE_STRIP,
-                0,
-                (vertices.len() / 2) as gl::types::GLsizei,
-            );
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-        }
+        self.batch_texture = Some(texture.get_id());
+        self.batch_premultiplied = texture.is_premultiplied();
+        self.batch.push_strip(vertices, colors, uv);
     }
 
     /// Draws a set of colored vertices to the screen, with a specified color array.
     fn draw_colored_vertices(&mut self, vertices: &[f32], colors: &[f32]) {
         self.configure_state(DrawState::Colored);
 
-        self.vertex.set_data(vertices);
-        self.color.set_data(colors);
+        self.batch_texture = None;
+        self.batch.push_strip(vertices, colors, &[]);
+    }
+
+    /// Submits all batched draws in a single draw call.
+    fn flush(&mut self) {
+        if self.batch.is_empty() {
+            self.delete_released_textures();
+            return;
+        }
+
+        self.vertex.set_data(&self.batch.vertices);
+        self.color.set_data(&self.batch.colors);
+
+        if let Some(texture) = self.batch_texture {
+            self.uv.set_data(&self.batch.uv);
+
+            unsafe {
+                gl::Uniform1i(
+                    self.uniform_textured_premultiply,
+                    self.premultiplied_alpha as gl::types::GLint,
+                );
+                gl::Uniform1i(
+                    self.uniform_textured_premultiplied_tex,
+                    self.batch_premultiplied as gl::types::GLint,
+                );
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+            }
+        } else {
+            unsafe {
+                gl::Uniform1i(
+                    self.uniform_colored_premultiply,
+                    self.premultiplied_alpha as gl::types::GLint,
+                );
+            }
+        }
 
         unsafe {
-            gl::DrawArrays(
-                gl::TRIANGLE_STRIP,
-                0,
-                (vertices.len() / 2) as gl::types::GLsizei,
-            )
+            gl::DrawArrays(gl::TRIANGLES, 0, self.batch.len() as gl::types::GLsizei);
+
+            if self.batch_texture.is_some() {
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+            }
         }
+
+        self.batch.clear();
+        self.delete_released_textures();
     }
 
     fn get_transition_count(&self) -> usize {
         self.transition_count
     }
 
-    fn new(title: &str, width: u32, height: u32) -> Result<Self, String> {
+    /// Combines the specified transform with the current one.
+    fn push_transform(&mut self, transform: &Transform) {
+        self.transforms.push(transform);
+    }
+
+    /// Restores the transform which was in effect before the last push.
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+
+    /// Returns the transform which is currently applied to draws.
+    fn get_transform(&self) -> Transform {
+        self.transforms.get()
+    }
+
+    /// Restricts following draws to the specified rectangle.
+    fn push_clip(&mut self, rect: &Rect) {
+        let rect = self.get_transform().bounding_rect(rect);
+        self.clips.push(&rect);
+        self.apply_clip();
+    }
+
+    /// Restores the clip which was in effect before the last push.
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+        self.apply_clip();
+    }
+
+    /// Returns the area which draws are currently restricted to.
+    fn get_clip(&self) -> Option<Rect> {
+        self.clips.get()
+    }
+
+    /// Creates a new texture, backed by a framebuffer object.
+    fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderTarget<Self::NativeTexture>, Error> {
+        // Creating the framebuffer changes which one is bound
+        self.flush();
+        let texture =
+            GlTexture::new_target(width as usize, height as usize, self.premultiplied_alpha);
+        self.bind_output();
+
+        Ok(RenderTarget::new(
+            texture?.with_release_queue(&self.releases),
+        ))
+    }
+
+    /// Redirects following draws into the specified target, or the screen.
+    fn set_render_target(&mut self, target: Option<&RenderTarget<Self::NativeTexture>>) {
+        self.flush();
+
+        self.target = target.map(|target| {
+            let texture = target.get_texture();
+
+            BoundTarget {
+                framebuffer: texture
+                    .get_framebuffer()
+                    .expect("Render target has no framebuffer")
+                    .get_id(),
+                width: texture.get_width(),
+                height: texture.get_height(),
+            }
+        });
+
+        self.bind_output();
+    }
+
+    /// Reads back the framebuffer, or the current render target. Must be called before
+    /// `end()`, as the contents of the back buffer are undefined once it has been swapped.
+    fn capture(&mut self) -> RgbaImage {
+        self.flush();
+
+        let (width, height) = self.get_size();
+        let mut pixels = vec![0; (width * height * 4) as usize];
+
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as gl::types::GLsizei,
+                height as gl::types::GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+        }
+
+        let image = RgbaImage::from_raw(width, height, pixels)
+            .expect("Framebuffer did not match its own size");
+
+        // OpenGL returns the bottom row first, but render targets are already flipped
+        if self.target.is_some() {
+            image
+        } else {
+            imageops::flip_vertical(&image)
+        }
+    }
+
+    fn with_config(config: DrawerConfig) -> Result<Self, Error> {
         let events_loop = glutin::EventsLoop::new();
-        let window = glutin::WindowBuilder::new()
-            .with_title(title)
-            .with_dimensions(LogicalSize::new(f64::from(width), f64::from(height)));
+        let mut config_warnings = Vec::new();
+
+        let mut window = glutin::WindowBuilder::new()
+            .with_title(config.title)
+            .with_dimensions(LogicalSize::new(
+                f64::from(config.width),
+                f64::from(config.height),
+            ))
+            .with_resizable(config.resizable)
+            .with_decorations(config.decorations);
+
+        if config.fullscreen {
+            window = window.with_fullscreen(Some(events_loop.get_primary_monitor()));
+        }
+
+        if let Some((width, height)) = config.min_size {
+            window =
+                window.with_min_dimensions(LogicalSize::new(f64::from(width), f64::from(height)));
+        }
+
+        if let Some(icon) = config.icon {
+            let (width, height) = icon.dimensions();
+            match glutin::Icon::from_rgba(icon.into_raw(), width, height) {
+                Ok(icon) => window = window.with_window_icon(Some(icon)),
+                Err(err) => config_warnings.push(format!("Window icon was ignored: {}", err)),
+            }
+        }
+
         let gl_window = glutin::ContextBuilder::new()
             .with_gl(glutin::GlRequest::Latest)
             .with_gl_profile(glutin::GlProfile::Core)
-            .with_vsync(true)
+            .with_vsync(config.vsync)
+            .with_multisampling(config.samples)
             .build_windowed(window, &events_loop)
-            .map_err(|x| format!("Failed to create glutin context: {}", x))?;
+            .map_err(|x| Error::Context(format!("Failed to create glutin context: {}", x)))?;
 
         let gl_window = unsafe {
-            gl_window
-                .make_current()
-                .map_err(|(_, err)| format!("{}", err))?
+            gl_window.make_current().map_err(|(_, err)| {
+                Error::Context(format!("Failed to make context current: {}", err))
+            })?
         };
 
         let (width, height): (u32, u32) = gl_window
             .window()
             .get_inner_size()
-            .ok_or_else(|| "Failed to get window size".to_string())?
+            .ok_or_else(|| Error::Context("Failed to get window size".into()))?
+            .to_physical(gl_window.window().get_hidpi_factor())
             .into();
 
-        unsafe {
-            gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
-
-            gl::DebugMessageCallback(gl_debug_message, ptr::null_mut());
-
-            gl::ClearColor(0.0, 1.0, 0.0, 1.0);
-            gl::Viewport(0, 0, width as i32, height as i32);
-        }
+        gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
 
-        let vertex_vbo = GLVBO::new();
-        let color_vbo = GLVBO::new();
-        let uv_vbo = GLVBO::new();
+        let mut drawer = GlutinDrawer::from_context(
+            events_loop,
+            GlutinContext::Windowed(gl_window),
+            width,
+            height,
+        )?;
+        drawer.config_warnings = config_warnings;
+        drawer.logical_units = config.logical_units;
+        drawer.update_window_size();
 
-        unsafe {
-            let mut ptr = mem::uninitialized();
-            gl::GenVertexArrays(1, &mut ptr);
-            gl::BindVertexArray(ptr);
-        }
+        Ok(drawer)
+    }
 
-        let colored_shader = GLSLShader::create_shader(
-            include_bytes!("../../../res/glutin_shaders/color.vert"),
-            include_bytes!("../../../res/glutin_shaders/color.frag"),
-        )?;
+    fn config_warnings(&self) -> &[String] {
+        &self.config_warnings
+    }
 
-        colored_shader.use_program();
-        let attr_colored_vertex = colored_shader.get_attribute("input_vertex");
-        let attr_colored_color = colored_shader.get_attribute("input_color");
+    fn new_headless(width: u32, height: u32) -> Result<Self, Error> {
+        let events_loop = glutin::EventsLoop::new();
+        let context = glutin::ContextBuilder::new()
+            .with_gl(glutin::GlRequest::Latest)
+            .with_gl_profile(glutin::GlProfile::Core)
+            .build_headless(
+                &events_loop,
+                PhysicalSize::new(f64::from(width), f64::from(height)),
+            )
+            .map_err(|x| {
+                Error::Context(format!("Failed to create headless glutin context: {}", x))
+            })?;
+
+        let context = unsafe {
+            context.make_current().map_err(|(_, err)| {
+                Error::Context(format!("Failed to make context current: {}", err))
+            })?
+        };
 
-        let textured_shader = GLSLShader::create_shader(
-            include_bytes!("../../../res/glutin_shaders/tex.vert"),
-            include_bytes!("../../../res/glutin_shaders/tex.frag"),
-        )?;
+        gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
 
-        textured_shader.use_program();
-        let attr_textured_vertex = textured_shader.get_attribute("input_vertex");
-        let attr_textured_color = textured_shader.get_attribute("input_color");
-        let attr_textured_uv = textured_shader.get_attribute("input_uv");
+        // Headless contexts aren't guaranteed to have a default framebuffer, so
+        // draw into a texture of our own
+        let texture = GlTexture::empty(width as usize, height as usize);
+        let framebuffer = GLFramebuffer::new(&texture)?;
+        framebuffer.bind();
 
-        Ok(GlutinDrawer {
+        GlutinDrawer::from_context(
             events_loop,
-            gl_window,
-            colored: colored_shader,
-            textured: textured_shader,
-            state: DrawState::None,
-            vertex: vertex_vbo,
-            attr_colored_vertex,
-            attr_textured_vertex,
-            color: color_vbo,
-            attr_colored_color,
-            attr_textured_color,
-            uv: uv_vbo,
-            attr_textured_uv,
-            background: None,
-            transition_count: 0,
-            calls: 0,
-        })
+            GlutinContext::Headless {
+                framebuffer,
+                texture,
+                context,
+            },
+            width,
+            height,
+        )
     }
 }
 
@@ -423,5 +931,5 @@ unsafe fn cstring_to_string(mut cs: *const c_char) -> String {
         v.push(*cs as u8);
         cs = cs.offset(1);
     }
-    String::from_utf8(v).expect("c-string not utf8")
+    String::from_utf8_lossy(&v).into_owned()
 }
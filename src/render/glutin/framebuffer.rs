@@ -0,0 +1,69 @@
+//! Manages OpenGL framebuffer objects.
+//!
+use crate::render::glutin::texture::GlTexture;
+use crate::Error;
+
+use gl;
+
+pub struct GLFramebuffer {
+    ptr: gl::types::GLuint,
+}
+
+impl GLFramebuffer {
+    /// Binds this framebuffer, redirecting all drawing into its attached texture.
+    pub fn bind(&self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.ptr) }
+    }
+
+    /// Returns the raw OpenGL name of this framebuffer.
+    pub fn get_id(&self) -> gl::types::GLuint {
+        self.ptr
+    }
+
+    /// Binds the default (window) framebuffer.
+    pub fn unbind() {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0) }
+    }
+
+    /// Creates a new framebuffer, with the specified texture as its color attachment.
+    /// The texture must outlive this framebuffer.
+    pub fn new(texture: &GlTexture) -> Result<Self, Error> {
+        let mut ptr = 0;
+
+        unsafe {
+            gl::GenFramebuffers(1, &mut ptr);
+        }
+
+        let framebuffer = GLFramebuffer { ptr };
+        framebuffer.bind();
+
+        let status = unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture.get_id(),
+                0,
+            );
+
+            gl::CheckFramebufferStatus(gl::FRAMEBUFFER)
+        };
+
+        GLFramebuffer::unbind();
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(Error::Texture(format!(
+                "Framebuffer is incomplete: {:#x}",
+                status
+            )));
+        }
+
+        Ok(framebuffer)
+    }
+}
+
+impl Drop for GLFramebuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteFramebuffers(1, [self.ptr].as_ptr()) }
+    }
+}
@@ -1,3 +1,4 @@
+mod framebuffer;
 mod shader;
 mod texture;
 mod vbo;
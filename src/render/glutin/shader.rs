@@ -7,6 +7,9 @@ use std::ptr;
 
 use std::ffi::CString;
 
+use crate::error::ShaderStage;
+use crate::Error;
+
 pub struct GLSLShader {
     program: gl::types::GLuint,
     vertex: gl::types::GLuint,
@@ -27,70 +30,43 @@ impl GLSLShader {
         unsafe { gl::GetAttribLocation(self.program, string.as_ptr()) }
     }
 
+    /// Returns the location of a uniform variable in this program.
+    pub fn get_uniform(&self, name: &str) -> gl::types::GLint {
+        let string = CString::new(name).unwrap();
+        unsafe { gl::GetUniformLocation(self.program, string.as_ptr()) }
+    }
+
     /// Creates a new shader.
-    /// Returns: Shader if compile succeeded, msg if failed.
-    pub fn create_shader(vertex: &[u8], frag: &[u8]) -> Result<GLSLShader, String> {
+    /// Returns: Shader if compile succeeded, the info log if failed.
+    pub fn create_shader(vertex: &[u8], frag: &[u8]) -> Result<GLSLShader, Error> {
         unsafe {
-            let mut status = GLint::from(gl::FALSE);
+            let vert_shader = compile_shader(gl::VERTEX_SHADER, ShaderStage::Vertex, vertex)?;
+            let frag_shader = match compile_shader(gl::FRAGMENT_SHADER, ShaderStage::Fragment, frag)
+            {
+                Ok(shader) => shader,
+                Err(err) => {
+                    gl::DeleteShader(vert_shader);
+                    return Err(err);
+                }
+            };
 
             // Create our shader program
             let program = gl::CreateProgram();
-
-            // Create our vertex shader
-            let vert_shader = gl::CreateShader(gl::VERTEX_SHADER);
-
-            let vert_ptr = CString::new(vertex).unwrap();
-
-            gl::ShaderSource(vert_shader, 1, [vert_ptr.as_ptr()].as_ptr(), ptr::null());
-
-            gl::CompileShader(vert_shader);
-            gl::GetShaderiv(vert_shader, gl::COMPILE_STATUS, &mut status);
-
-            if status == GLint::from(gl::FALSE) {
-                let mut len: GLint = 0;
-                gl::GetShaderiv(vert_shader, gl::INFO_LOG_LENGTH, &mut len);
-                let mut buf: Vec<u8> = vec![0; len as usize];
-                gl::GetShaderInfoLog(
-                    vert_shader,
-                    len,
-                    ptr::null_mut(),
-                    buf.as_mut_ptr() as *mut gl::types::GLchar,
-                );
-                return Err(String::from_utf8(buf).expect("ProgramInfoLog not valid utf8"));
-            }
-
             gl::AttachShader(program, vert_shader);
-
-            // Create our fragment shader
-            let frag_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-
-            let frag_ptr = CString::new(frag).unwrap();
-
-            gl::ShaderSource(frag_shader, 1, [frag_ptr.as_ptr()].as_ptr(), ptr::null());
-
-            gl::CompileShader(frag_shader);
-            gl::GetShaderiv(frag_shader, gl::COMPILE_STATUS, &mut status);
-
-            if status == GLint::from(gl::FALSE) {
-                let mut len: GLint = 0;
-                gl::GetShaderiv(frag_shader, gl::INFO_LOG_LENGTH, &mut len);
-                let mut buf: Vec<u8> = vec![0; len as usize];
-                gl::GetShaderInfoLog(
-                    frag_shader,
-                    len,
-                    ptr::null_mut(),
-                    buf.as_mut_ptr() as *mut gl::types::GLchar,
-                );
-                return Err(String::from_utf8(buf).expect("ProgramInfoLog not valid utf8"));
-            }
-
             gl::AttachShader(program, frag_shader);
 
             // Compile and link
             gl::LinkProgram(program);
 
+            let mut status = GLint::from(gl::FALSE);
             gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
 
+            let shader = GLSLShader {
+                program,
+                vertex: vert_shader,
+                fragment: frag_shader,
+            };
+
             if status == GLint::from(gl::FALSE) {
                 let mut len: GLint = 0;
                 gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
@@ -101,18 +77,64 @@ impl GLSLShader {
                     ptr::null_mut(),
                     buf.as_mut_ptr() as *mut gl::types::GLchar,
                 );
-                Err(String::from_utf8(buf).expect("ProgramInfoLog not valid utf8"))
-            } else {
-                Ok(GLSLShader {
-                    program,
-                    vertex: vert_shader,
-                    fragment: frag_shader,
+                Err(Error::ShaderLink {
+                    log: log_to_string(buf),
                 })
+            } else {
+                Ok(shader)
             }
         }
     }
 }
 
+/// Compiles a single stage of a shader program, returning the info log if it fails.
+unsafe fn compile_shader(
+    kind: gl::types::GLenum,
+    stage: ShaderStage,
+    source: &[u8],
+) -> Result<gl::types::GLuint, Error> {
+    let source = CString::new(source).map_err(|_| Error::ShaderCompile {
+        stage,
+        log: "Shader source contains a nul byte".into(),
+    })?;
+
+    let shader = gl::CreateShader(kind);
+    gl::ShaderSource(shader, 1, [source.as_ptr()].as_ptr(), ptr::null());
+    gl::CompileShader(shader);
+
+    let mut status = GLint::from(gl::FALSE);
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+
+    if status == GLint::from(gl::FALSE) {
+        let mut len: GLint = 0;
+        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+        let mut buf: Vec<u8> = vec![0; len as usize];
+        gl::GetShaderInfoLog(
+            shader,
+            len,
+            ptr::null_mut(),
+            buf.as_mut_ptr() as *mut gl::types::GLchar,
+        );
+        gl::DeleteShader(shader);
+
+        return Err(Error::ShaderCompile {
+            stage,
+            log: log_to_string(buf),
+        });
+    }
+
+    Ok(shader)
+}
+
+/// Converts a info log from the driver into a string, without its trailing nul.
+fn log_to_string(mut buf: Vec<u8>) -> String {
+    while buf.last() == Some(&0) {
+        buf.pop();
+    }
+
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
 impl Drop for GLSLShader {
     fn drop(&mut self) {
         unsafe {
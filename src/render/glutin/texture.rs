@@ -1,80 +1,300 @@
 //! Manages OpenGL textures.
 //!
+use crate::pos::Rect;
+use crate::render::glutin::framebuffer::GLFramebuffer;
+use crate::render::next_texture_id;
+use crate::render::premultiply_pixels;
 use crate::render::Dimensions;
+use crate::render::PixelFormat;
 use crate::render::Texture;
+use crate::render::TextureFilter;
+use crate::render::TextureOptions;
+use crate::render::TextureWrap;
+use crate::render::UniqueTexture;
+use crate::Error;
 
-use gl;
+use std::sync::Arc;
+use std::sync::Mutex;
 
-use std::mem;
+use gl;
 
 use image::RgbaImage;
 
+/// Returns the internal format, format and type which OpenGL stores pixels of the specified
+/// format with.
+fn gl_format(format: PixelFormat) -> (gl::types::GLint, gl::types::GLenum, gl::types::GLenum) {
+    match format {
+        PixelFormat::Rgba8888 => (gl::RGBA8 as _, gl::RGBA, gl::UNSIGNED_BYTE),
+        PixelFormat::Rgb888 => (gl::RGB8 as _, gl::RGB, gl::UNSIGNED_BYTE),
+        PixelFormat::Rgb565 => (gl::RGB as _, gl::RGB, gl::UNSIGNED_SHORT_5_6_5),
+        PixelFormat::Rgba4444 => (gl::RGBA4 as _, gl::RGBA, gl::UNSIGNED_SHORT_4_4_4_4),
+        // Core profiles have no alpha or luminance textures, so these are swizzled instead
+        PixelFormat::Alpha8 | PixelFormat::Luminance8 => (gl::R8 as _, gl::RED, gl::UNSIGNED_BYTE),
+    }
+}
+
+/// Textures which have been dropped, but may still be used by draws which haven't been
+/// flushed yet. These are deleted by the drawer once it has flushed.
+pub(crate) type ReleaseQueue = Arc<Mutex<Vec<gl::types::GLuint>>>;
+
 pub struct GlTexture {
+    unique_id: u64,
     width: usize,
     height: usize,
     ptr: gl::types::GLuint,
+    format: PixelFormat,
+    premultiplied: bool,
+    mipmaps: bool,
+    // Set if this texture can be drawn into
+    framebuffer: Option<GLFramebuffer>,
+    // Where this texture is handed over to when dropped, so draws still waiting on it
+    releases: Option<ReleaseQueue>,
 }
 
 impl GlTexture {
-    /// Converts a RGBA byte array to a OpenGL reference.
-    fn from_bytes(bytes: &[u8], width: usize, height: usize) -> Self {
-        let mut texture_ref = unsafe { mem::uninitialized() };
+    /// Converts a byte array of pixels to a OpenGL reference, optionally premultiplying it.
+    fn from_bytes(
+        bytes: &[u8],
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        premultiply: bool,
+        options: &TextureOptions,
+    ) -> Self {
+        let premultiply = premultiply && format.can_premultiply();
+
+        let premultiplied_bytes;
+        let bytes = if premultiply {
+            premultiplied_bytes = premultiply_pixels(bytes, format);
+            &premultiplied_bytes
+        } else {
+            bytes
+        };
+
+        let (internal_format, data_format, data_type) = gl_format(format);
+
+        let mut texture_ref = 0;
         unsafe {
             gl::GenTextures(1, &mut texture_ref);
             gl::BindTexture(gl::TEXTURE_2D, texture_ref);
             gl::TexImage2D(
                 gl::TEXTURE_2D,
-                0 as gl::types::GLint,
-                gl::RGBA as gl::types::GLint,
+                0,
+                internal_format,
                 width as gl::types::GLint,
                 height as gl::types::GLint,
                 0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
+                data_format,
+                data_type,
                 bytes.as_ptr() as *const _,
             );
 
+            let swizzle = match format {
+                PixelFormat::Alpha8 => Some([gl::ONE, gl::ONE, gl::ONE, gl::RED]),
+                PixelFormat::Luminance8 => Some([gl::RED, gl::RED, gl::RED, gl::ONE]),
+                _ => None,
+            };
+
+            if let Some(swizzle) = swizzle {
+                let swizzle = [
+                    swizzle[0] as gl::types::GLint,
+                    swizzle[1] as gl::types::GLint,
+                    swizzle[2] as gl::types::GLint,
+                    swizzle[3] as gl::types::GLint,
+                ];
+                gl::TexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+            }
+
+            let mipmaps = options.needs_mipmaps();
+
+            let (min_filter, mag_filter) = match (options.filter, mipmaps) {
+                (TextureFilter::Nearest, false) => (gl::NEAREST, gl::NEAREST),
+                (TextureFilter::Nearest, true) => (gl::NEAREST_MIPMAP_NEAREST, gl::NEAREST),
+                (TextureFilter::Linear, false) => (gl::LINEAR, gl::LINEAR),
+                (TextureFilter::Linear, true) => (gl::LINEAR_MIPMAP_NEAREST, gl::LINEAR),
+                (TextureFilter::Trilinear, _) => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
+            };
+
+            let wrap = match options.wrap {
+                TextureWrap::Clamp => gl::CLAMP_TO_EDGE,
+                TextureWrap::Repeat => gl::REPEAT,
+                TextureWrap::Mirror => gl::MIRRORED_REPEAT,
+            };
+
             gl::TexParameteri(
                 gl::TEXTURE_2D,
                 gl::TEXTURE_MIN_FILTER,
-                gl::LINEAR as gl::types::GLint,
+                min_filter as gl::types::GLint,
             );
             gl::TexParameteri(
                 gl::TEXTURE_2D,
                 gl::TEXTURE_MAG_FILTER,
-                gl::LINEAR as gl::types::GLint,
+                mag_filter as gl::types::GLint,
             );
 
-            gl::TexParameteri(
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap as gl::types::GLint);
+
+            if mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        GlTexture {
+            unique_id: next_texture_id(),
+            width,
+            height,
+            ptr: texture_ref,
+            format,
+            premultiplied: premultiply,
+            mipmaps: options.needs_mipmaps(),
+            framebuffer: None,
+            releases: None,
+        }
+    }
+
+    /// Replaces the pixels in a region of this texture with a byte array in the format of
+    /// this texture, which must exactly fill the region. The bytes are premultiplied first
+    /// if this texture is.
+    pub fn update(&mut self, region: &Rect, bytes: &[u8]) {
+        if region.width <= 0 || region.height <= 0 {
+            return;
+        }
+
+        let premultiplied_bytes;
+        let bytes = if self.premultiplied {
+            premultiplied_bytes = premultiply_pixels(bytes, self.format);
+            &premultiplied_bytes
+        } else {
+            bytes
+        };
+
+        let (_, data_format, data_type) = gl_format(self.format);
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.ptr);
+            gl::TexSubImage2D(
                 gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_S,
-                gl::CLAMP_TO_EDGE as gl::types::GLint,
+                0,
+                region.x as gl::types::GLint,
+                region.y as gl::types::GLint,
+                region.width as gl::types::GLint,
+                region.height as gl::types::GLint,
+                data_format,
+                data_type,
+                bytes.as_ptr() as *const _,
             );
-            gl::TexParameteri(
+
+            if self.mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    /// Changes the size of this texture, keeping the same OpenGL texture and its sampling
+    /// options. The contents become fully transparent.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let bytes = vec![0u8; width * height * self.format.bytes_per_pixel()];
+        let (internal_format, data_format, data_type) = gl_format(self.format);
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.ptr);
+            gl::TexImage2D(
                 gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_T,
-                gl::CLAMP_TO_EDGE as gl::types::GLint,
+                0,
+                internal_format,
+                width as gl::types::GLint,
+                height as gl::types::GLint,
+                0,
+                data_format,
+                data_type,
+                bytes.as_ptr() as *const _,
             );
 
-            //gl::GenerateMipmap(gl::TEXTURE_2D);
+            if self.mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
 
-        GlTexture {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Creates a new, fully transparent texture.
+    pub fn empty(width: usize, height: usize) -> Self {
+        GlTexture::from_bytes(
+            &vec![0; width * height * 4],
             width,
             height,
-            ptr: texture_ref,
-        }
+            PixelFormat::Rgba8888,
+            false,
+            &TextureOptions::default(),
+        )
+    }
+
+    /// Creates a new, fully transparent texture which can be drawn into. Premultiplied
+    /// targets expect to be drawn into with the premultiplied alpha pipeline.
+    pub fn new_target(width: usize, height: usize, premultiplied: bool) -> Result<Self, Error> {
+        let mut texture = GlTexture::empty(width, height);
+        texture.premultiplied = premultiplied;
+        texture.framebuffer = Some(GLFramebuffer::new(&texture)?);
+        Ok(texture)
+    }
+
+    /// Converts a texture to a OpenGL reference, optionally premultiplying it.
+    pub fn from_texture(tex: &Texture, premultiply: bool, options: &TextureOptions) -> Self {
+        GlTexture::from_bytes(
+            &tex.tex_data,
+            tex.get_width(),
+            tex.get_height(),
+            tex.get_format(),
+            premultiply,
+            options,
+        )
+    }
+
+    /// Converts a image to a OpenGL reference, optionally premultiplying it.
+    pub fn from_image(tex: &RgbaImage, premultiply: bool, options: &TextureOptions) -> Self {
+        GlTexture::from_bytes(
+            tex.as_ref(),
+            tex.width() as usize,
+            tex.height() as usize,
+            PixelFormat::Rgba8888,
+            premultiply,
+            options,
+        )
+    }
+
+    /// Returns the layout of the pixels in this texture.
+    pub fn get_format(&self) -> PixelFormat {
+        self.format
     }
 
-    /// Converts a texture to a OpenGL reference.
-    pub fn from_texture(tex: &Texture) -> Self {
-        GlTexture::from_bytes(&tex.tex_data, tex.get_width(), tex.get_height())
+    /// Checks if the colors of this texture have been multiplied by their alpha.
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
     }
 
-    /// Converts a image to a OpenGL reference.
-    pub fn from_image(tex: &RgbaImage) -> Self {
-        GlTexture::from_bytes(tex.as_ref(), tex.width() as usize, tex.height() as usize)
+    /// Returns the raw OpenGL name of this texture.
+    pub fn get_id(&self) -> gl::types::GLuint {
+        self.ptr
+    }
+
+    /// Defers deleting this texture once dropped until the owner of the queue has flushed.
+    pub(crate) fn with_release_queue(mut self, releases: &ReleaseQueue) -> Self {
+        self.releases = Some(releases.clone());
+        self
+    }
+
+    /// Returns the framebuffer which draws into this texture, if this is a render target.
+    pub fn get_framebuffer(&self) -> Option<&GLFramebuffer> {
+        self.framebuffer.as_ref()
     }
 
     /// Binds this OpenGL texture. This struct must
@@ -96,8 +316,22 @@ impl Dimensions for GlTexture {
     }
 }
 
+impl UniqueTexture for GlTexture {
+    /// Returns the ID this texture was given when created.
+    fn get_unique_id(&self) -> u64 {
+        self.unique_id
+    }
+}
+
 impl Drop for GlTexture {
     fn drop(&mut self) {
+        if let Some(releases) = &self.releases {
+            if let Ok(mut releases) = releases.lock() {
+                releases.push(self.ptr);
+                return;
+            }
+        }
+
         unsafe {
             gl::DeleteTextures(1, [self.ptr].as_ptr());
         }
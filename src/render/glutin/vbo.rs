@@ -4,6 +4,7 @@ use gl;
 
 use std::mem;
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct GLVBO {
     ptr: gl::types::GLuint,
 }
@@ -20,7 +21,7 @@ impl GLVBO {
         unsafe {
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (data.len() * mem::size_of::<T>()) as gl::types::GLsizeiptr,
+                mem::size_of_val(data) as gl::types::GLsizeiptr,
                 data.as_ptr() as *const _,
                 gl::STATIC_DRAW,
             )
@@ -29,7 +30,7 @@ impl GLVBO {
 
     /// Creates a new OpenGL VBO.
     pub fn new() -> Self {
-        let mut ptr = unsafe { mem::uninitialized() };
+        let mut ptr = 0;
 
         unsafe {
             gl::GenBuffers(1, &mut ptr);
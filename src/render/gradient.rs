@@ -0,0 +1,528 @@
+//! Linear and radial gradients, which can be used to fill shapes instead of a single color.
+
+use crate::pos::Position;
+use crate::render::line::arc_segments;
+use crate::render::line::push_triangle;
+use crate::render::Color;
+
+use std::f32::consts::PI;
+
+/// A color at a point along a gradient.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientStop {
+    /// How far along the gradient this color is, usually from 0 to 1.
+    pub offset: f32,
+    pub color: Color,
+}
+
+impl GradientStop {
+    pub fn new(offset: f32, color: Color) -> Self {
+        GradientStop { offset, color }
+    }
+}
+
+/// A smooth blend between several colors. Before the first stop and after the last, the
+/// color of the closest stop is used.
+///
+/// ```rust
+/// use leafrender::pos::Position;
+/// use leafrender::render::Color;
+/// use leafrender::render::gradient::{Gradient, GradientStop};
+///
+/// let sky = Gradient::linear(
+///     Position::new(0, 0),
+///     Position::new(0, 480),
+///     vec![
+///         GradientStop::new(0.0, Color::new_3byte(20, 40, 120)),
+///         GradientStop::new(0.7, Color::new_3byte(120, 160, 240)),
+///         GradientStop::new(1.0, Color::new_3byte(250, 200, 150)),
+///     ],
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Gradient {
+    /// Colors change along the line between two points, and stay the same across it.
+    Linear {
+        from: Position,
+        to: Position,
+        stops: Vec<GradientStop>,
+    },
+    /// Colors change with the distance from a center point, out to the specified radius.
+    Radial {
+        center: Position,
+        radius: f32,
+        stops: Vec<GradientStop>,
+    },
+}
+
+impl Gradient {
+    /// Creates a gradient along the line between two points, where offset 0 is the first
+    /// point and offset 1 is the second. Stops with offsets which aren't finite are left
+    /// out.
+    pub fn linear(from: Position, to: Position, mut stops: Vec<GradientStop>) -> Self {
+        sort_stops(&mut stops);
+        Gradient::Linear { from, to, stops }
+    }
+
+    /// Creates a gradient around a center point, where offset 0 is the center and offset 1
+    /// is the specified radius, in pixels. Stops with offsets which aren't finite are left
+    /// out.
+    pub fn radial(center: Position, radius: f32, mut stops: Vec<GradientStop>) -> Self {
+        sort_stops(&mut stops);
+        Gradient::Radial {
+            center,
+            radius,
+            stops,
+        }
+    }
+
+    /// Returns the stops of this gradient.
+    pub fn get_stops(&self) -> &[GradientStop] {
+        match *self {
+            Gradient::Linear { ref stops, .. } | Gradient::Radial { ref stops, .. } => stops,
+        }
+    }
+
+    /// Returns how far along the gradient a point is.
+    fn offset_at(&self, x: f32, y: f32) -> f32 {
+        match *self {
+            Gradient::Linear { from, to, .. } => {
+                let axis_x = (to.x - from.x) as f32;
+                let axis_y = (to.y - from.y) as f32;
+                let length = axis_x * axis_x + axis_y * axis_y;
+
+                if length == 0.0 {
+                    return 0.0;
+                }
+
+                ((x - from.x as f32) * axis_x + (y - from.y as f32) * axis_y) / length
+            }
+            Gradient::Radial { center, radius, .. } => {
+                if radius <= 0.0 {
+                    return 1.0;
+                }
+
+                let x = x - center.x as f32;
+                let y = y - center.y as f32;
+                (x * x + y * y).sqrt() / radius
+            }
+        }
+    }
+
+    /// Returns the color of this gradient at a point, as RGBA values from 0 to 1.
+    pub fn color_at(&self, x: f32, y: f32) -> [f32; 4] {
+        let stops = self.get_stops();
+        let offset = self.offset_at(x, y);
+
+        let (first, last) = match (stops.first(), stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [0.0; 4],
+        };
+
+        if offset <= first.offset {
+            return to_floats(&first.color);
+        }
+
+        for pair in stops.windows(2) {
+            let (before, after) = (&pair[0], &pair[1]);
+
+            if offset <= after.offset {
+                let range = after.offset - before.offset;
+                let t = if range > 0.0 {
+                    (offset - before.offset) / range
+                } else {
+                    1.0
+                };
+
+                let before = to_floats(&before.color);
+                let after = to_floats(&after.color);
+
+                let mut color = [0.0; 4];
+                for i in 0..4 {
+                    color[i] = before[i] + (after[i] - before[i]) * t;
+                }
+                return color;
+            }
+        }
+
+        to_floats(&last.color)
+    }
+
+    /// Splits a list of triangles (x/y pairs, 3 points per triangle) up so that the colors
+    /// of this gradient can be interpolated between their corners, once scaled by the
+    /// specified amount. Returns the new triangles, and a color for each point. Triangles
+    /// with points which aren't finite are left out.
+    pub(crate) fn paint_triangles(&self, triangles: &[f32], scale: f32) -> (Vec<f32>, Vec<f32>) {
+        let mut pieces = Vec::with_capacity(triangles.len() / 6);
+
+        for triangle in triangles.chunks(6) {
+            if triangle.len() < 6 {
+                break;
+            }
+
+            if triangle.iter().any(|value| !value.is_finite()) {
+                continue;
+            }
+
+            pieces.push(vec![
+                (triangle[0], triangle[1]),
+                (triangle[2], triangle[3]),
+                (triangle[4], triangle[5]),
+            ]);
+        }
+
+        let pieces = match *self {
+            Gradient::Linear { ref stops, .. } => {
+                // Linear gradients change evenly between stops, so cutting the triangles
+                // along each stop is exact
+                for stop in stops {
+                    pieces = pieces
+                        .into_iter()
+                        .flat_map(|piece| self.cut_polygon(&piece, stop.offset))
+                        .collect();
+                }
+
+                pieces
+            }
+            Gradient::Radial {
+                center,
+                radius,
+                ref stops,
+            } => {
+                // Colors only change with the distance from the center, and change evenly
+                // between stops, so the triangles are cut into wedges around the center and
+                // along a ring for each stop. This keeps the number of triangles the same no
+                // matter how large the gradient is drawn.
+                let mut rings: Vec<f32> = stops
+                    .iter()
+                    .map(|stop| stop.offset * radius)
+                    .filter(|ring| ring.is_finite() && *ring > 0.0)
+                    .collect();
+                rings.dedup();
+
+                match rings.last() {
+                    Some(&outer) => {
+                        let scale = if scale.is_finite() { scale } else { 1.0 };
+                        let wedges = arc_segments(outer * scale, 2.0 * PI);
+                        let center = (center.x as f32, center.y as f32);
+
+                        pieces
+                            .into_iter()
+                            .flat_map(|piece| cut_radial(&piece, center, &rings, wedges))
+                            .collect()
+                    }
+                    // The whole gradient is a single color
+                    None => pieces,
+                }
+            }
+        };
+
+        let mut split = Vec::with_capacity(pieces.len() * 6);
+        for piece in pieces {
+            for i in 1..piece.len() - 1 {
+                push_triangle(&mut split, piece[0], piece[i], piece[i + 1]);
+            }
+        }
+
+        let mut colors = Vec::with_capacity(split.len() * 2);
+        for point in split.chunks(2) {
+            colors.extend_from_slice(&self.color_at(point[0], point[1]));
+        }
+
+        (split, colors)
+    }
+
+    /// Cuts a convex polygon in two where it crosses the specified gradient offset. Pieces
+    /// with no area are left out.
+    fn cut_polygon(&self, polygon: &[(f32, f32)], offset: f32) -> Vec<Vec<(f32, f32)>> {
+        let (below, above) =
+            split_polygon(polygon, |point| self.offset_at(point.0, point.1) - offset);
+
+        vec![below, above]
+            .into_iter()
+            .filter(|piece| piece.len() >= 3)
+            .collect()
+    }
+}
+
+/// A convex polygon, as a list of points.
+type Polygon = Vec<(f32, f32)>;
+
+/// Cuts a convex polygon in two along a line, where `side` returns how far a point is from
+/// the line (negative on one side and positive on the other). Returns the pieces below and
+/// above the line, which are empty if the polygon doesn't reach that side.
+fn split_polygon<F>(polygon: &[(f32, f32)], side: F) -> (Polygon, Polygon)
+where
+    F: Fn((f32, f32)) -> f32,
+{
+    let mut below = Vec::new();
+    let mut above = Vec::new();
+
+    for (i, &point) in polygon.iter().enumerate() {
+        let next = polygon[(i + 1) % polygon.len()];
+        let point_offset = side(point);
+        let next_offset = side(next);
+
+        if point_offset <= 0.0 {
+            below.push(point);
+        }
+        if point_offset >= 0.0 {
+            above.push(point);
+        }
+
+        if (point_offset < 0.0 && next_offset > 0.0) || (point_offset > 0.0 && next_offset < 0.0) {
+            // Triangles sharing this edge must cut it at exactly the same point, or
+            // gaps appear between them, so always work from the lower end
+            let (low, low_offset, high, high_offset) = if point_offset < next_offset {
+                (point, point_offset, next, next_offset)
+            } else {
+                (next, next_offset, point, point_offset)
+            };
+
+            let t = low_offset / (low_offset - high_offset);
+            let cut = (low.0 + (high.0 - low.0) * t, low.1 + (high.1 - low.1) * t);
+            below.push(cut);
+            above.push(cut);
+        }
+    }
+
+    (below, above)
+}
+
+/// Cuts a convex polygon into pieces around a center point: first into the specified number
+/// of wedges (rounded up to an even number), then each wedge along the rings of the specified
+/// radii (sorted from the innermost). The rings are approximated by a straight line across
+/// each wedge, so every piece stays convex. Pieces with no area are left out.
+fn cut_radial(
+    polygon: &[(f32, f32)],
+    center: (f32, f32),
+    rings: &[f32],
+    wedges: usize,
+) -> Vec<Vec<(f32, f32)>> {
+    let half = wedges.div_ceil(2).max(1);
+    let step = PI / half as f32;
+
+    // The direction of the ray between two wedges as a (sin, cos) pair
+    let ray = |index: usize| ((index % (half * 2)) as f32 * step).sin_cos();
+
+    // How far around from a ray a point is
+    let around = |ray: (f32, f32), point: (f32, f32)| {
+        ray.1 * (point.1 - center.1) - ray.0 * (point.0 - center.0)
+    };
+
+    // Each half of the circle is swept one ray at a time, so that the edge between two
+    // wedges comes from a single cut, and both wedges agree on where it is
+    let (lower, upper) = split_polygon(polygon, |point| around(ray(0), point));
+
+    let mut wedge_pieces = Vec::new();
+    for (first, mut remaining) in [(0, upper), (half, lower)] {
+        for index in first..first + half {
+            if remaining.len() < 3 {
+                break;
+            }
+
+            let wedge = if index + 1 < first + half {
+                let (wedge, rest) =
+                    split_polygon(&remaining, |point| around(ray(index + 1), point));
+                remaining = rest;
+                wedge
+            } else {
+                std::mem::take(&mut remaining)
+            };
+
+            if wedge.len() >= 3 {
+                wedge_pieces.push((index, wedge));
+            }
+        }
+    }
+
+    let mut pieces = Vec::new();
+    for (index, mut remaining) in wedge_pieces {
+        let middle = ((index as f32 + 0.5) * step).sin_cos();
+
+        for &ring in rings {
+            if remaining.len() < 3 {
+                break;
+            }
+
+            // The distance of the line across this wedge from the center
+            let inset = ring * (step / 2.0).cos();
+            let (inside, outside) = split_polygon(&remaining, |point| {
+                (point.0 - center.0) * middle.1 + (point.1 - center.1) * middle.0 - inset
+            });
+
+            if inside.len() >= 3 {
+                pieces.push((index, inside));
+            }
+            remaining = outside;
+        }
+
+        if remaining.len() >= 3 {
+            pieces.push((index, remaining));
+        }
+    }
+
+    // Neighbouring wedges cut their shared edge along different lines, which meet it at
+    // slightly different points. Moving those points onto where the rings cross the rays
+    // keeps pixels from falling through the gap between them.
+    let snap = |point: &mut (f32, f32), index: usize| {
+        let mut targets = vec![center];
+        for &ring in rings {
+            for direction in [ray(index), ray(index + 1)] {
+                targets.push((center.0 + ring * direction.1, center.1 + ring * direction.0));
+            }
+        }
+
+        for target in targets {
+            // The points only differ by rounding, which grows with the size of the coordinates
+            let tolerance = 1e-5 * target.0.abs().max(target.1.abs()).max(1.0);
+            if (point.0 - target.0).abs() < tolerance && (point.1 - target.1).abs() < tolerance {
+                *point = target;
+                break;
+            }
+        }
+    };
+
+    pieces
+        .into_iter()
+        .map(|(index, mut piece)| {
+            for point in &mut piece {
+                snap(point, index);
+            }
+            piece
+        })
+        .collect()
+}
+
+/// Sorts stops by their offset, leaving out any which aren't finite.
+fn sort_stops(stops: &mut Vec<GradientStop>) {
+    stops.retain(|stop| stop.offset.is_finite());
+    stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+}
+
+fn to_floats(color: &Color) -> [f32; 4] {
+    [
+        f32::from(color.r) / 255.0,
+        f32::from(color.g) / 255.0,
+        f32::from(color.b) / 255.0,
+        f32::from(color.a) / 255.0,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pos::Rect;
+    use crate::render::shapes;
+
+    /// Sums the area of a list of triangles.
+    fn area(triangles: &[f32]) -> f32 {
+        triangles
+            .chunks(6)
+            .map(|t| ((t[2] - t[0]) * (t[5] - t[1]) - (t[4] - t[0]) * (t[3] - t[1])).abs() / 2.0)
+            .sum()
+    }
+
+    fn stops() -> Vec<GradientStop> {
+        vec![
+            GradientStop::new(0.0, Color::new_3byte(255, 0, 0)),
+            GradientStop::new(0.5, Color::new_3byte(0, 255, 0)),
+            GradientStop::new(1.0, Color::new_3byte(0, 0, 255)),
+        ]
+    }
+
+    #[test]
+    fn radial_mesh_is_bounded_for_screen_sized_rects() {
+        let screen = shapes::fill_rect(&Rect::new(0, 0, 1920, 1080));
+
+        for &radius in &[100.0, 1100.0, 1e9] {
+            let gradient = Gradient::radial(Position::new(960, 540), radius, stops());
+
+            for &scale in &[1.0, 4.0] {
+                let (triangles, colors) = gradient.paint_triangles(&screen, scale);
+
+                assert!(
+                    triangles.len() / 2 < 10_000,
+                    "{} vertices",
+                    triangles.len() / 2
+                );
+                assert_eq!(triangles.len() / 2, colors.len() / 4);
+
+                // None of the rect may be lost or covered twice
+                assert!((area(&triangles) / (1920.0 * 1080.0) - 1.0).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn radial_colors_follow_distance() {
+        let gradient = Gradient::radial(Position::new(50, 50), 40.0, stops());
+        let (triangles, colors) =
+            gradient.paint_triangles(&shapes::fill_rect(&Rect::new(0, 0, 100, 100)), 1.0);
+
+        for (point, color) in triangles.chunks(2).zip(colors.chunks(4)) {
+            assert_eq!(color, &gradient.color_at(point[0], point[1]));
+        }
+
+        // The center and every ring lie on the corners of triangles
+        let has_point = |x: f32, y: f32| {
+            triangles
+                .chunks(2)
+                .any(|point| (point[0] - x).abs() < 1e-3 && (point[1] - y).abs() < 1e-3)
+        };
+        assert!(has_point(50.0, 50.0));
+        assert!(has_point(70.0, 50.0));
+        assert!(has_point(90.0, 50.0));
+    }
+
+    #[test]
+    fn skips_values_which_are_not_finite() {
+        let rect = shapes::fill_rect(&Rect::new(0, 0, 100, 100));
+
+        for &radius in &[f32::NAN, f32::INFINITY, -10.0, 0.0] {
+            let gradient = Gradient::radial(Position::new(50, 50), radius, stops());
+            let (triangles, _) = gradient.paint_triangles(&rect, 1.0);
+            assert!((area(&triangles) - 10_000.0).abs() < 1e-1);
+        }
+
+        let gradient = Gradient::radial(Position::new(50, 50), 40.0, stops());
+        let (triangles, _) = gradient.paint_triangles(&[0.0, 0.0, f32::NAN, 5.0, 10.0, 10.0], 1.0);
+        assert!(triangles.is_empty());
+
+        let (triangles, _) = gradient.paint_triangles(&rect, f32::NAN);
+        assert!((area(&triangles) - 10_000.0).abs() < 1e-1);
+
+        // Enough stops that sorting them would notice a comparison which isn't a total order
+        let mut stops = Vec::new();
+        for i in 0..40 {
+            let offset = if i % 3 == 0 {
+                f32::NAN
+            } else {
+                (i % 7) as f32 / 7.0
+            };
+            stops.push(GradientStop::new(offset, Color::new_3byte(i as u8, 0, 0)));
+        }
+        stops.push(GradientStop::new(
+            f32::INFINITY,
+            Color::new_3byte(0, 0, 255),
+        ));
+
+        for gradient in &[
+            Gradient::linear(Position::new(0, 0), Position::new(100, 0), stops.clone()),
+            Gradient::radial(Position::new(50, 50), 40.0, stops.clone()),
+        ] {
+            let offsets: Vec<f32> = gradient
+                .get_stops()
+                .iter()
+                .map(|stop| stop.offset)
+                .collect();
+            assert_eq!(offsets.len(), 26);
+            assert!(offsets.windows(2).all(|pair| pair[0] <= pair[1]));
+
+            let (triangles, colors) = gradient.paint_triangles(&rect, 1.0);
+            assert!((area(&triangles) - 10_000.0).abs() < 1e-1);
+            assert!(colors.iter().all(|value| value.is_finite()));
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Tessellates thick lines into triangles.
+
+use std::f32::consts::PI;
+
+/// The ratio of miter length to line width beyond which miter joins become bevel joins,
+/// stopping sharp corners from producing long spikes.
+const MITER_LIMIT: f32 = 4.0;
+
+/// How the segments of a polyline are connected to each other.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum LineJoin {
+    /// The outer edges are extended until they meet in a sharp point. Very sharp corners
+    /// fall back to a bevel.
+    Miter,
+    /// The corner is rounded off, with the line width as its diameter.
+    Round,
+    /// The corner is cut off with a straight edge.
+    Bevel,
+}
+
+/// How the ends of a line are finished.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum LineCap {
+    /// The line stops exactly at its end points.
+    Butt,
+    /// The line is extended by a half circle, with the line width as its diameter.
+    Round,
+    /// The line is extended by half of its width.
+    Square,
+}
+
+/// Returns how many segments an arc of the specified radius and angle (in radians) should
+/// be split into, to look smooth. Angles beyond a full turn are treated as a full turn.
+pub(crate) fn arc_segments(radius: f32, angle: f32) -> usize {
+    // Aim for around 3 pixels per segment
+    let circle = (radius * 2.0 * PI / 3.0).ceil().clamp(12.0, 128.0);
+    ((circle * clamp_sweep(angle).abs() / (2.0 * PI)).ceil() as usize).max(1)
+}
+
+/// Limits how far an arc sweeps around to a full turn in either direction, as going any
+/// further only draws over the same pixels again.
+pub(crate) fn clamp_sweep(angle: f32) -> f32 {
+    angle.clamp(-2.0 * PI, 2.0 * PI)
+}
+
+/// Appends a triangle fan around a center point, along an arc from one angle to another.
+pub(crate) fn push_arc(
+    triangles: &mut Vec<f32>,
+    center: (f32, f32),
+    radius: f32,
+    start: f32,
+    end: f32,
+) {
+    let segments = arc_segments(radius, end - start);
+    let step = (end - start) / segments as f32;
+
+    let point = |angle: f32| {
+        (
+            center.0 + angle.cos() * radius,
+            center.1 + angle.sin() * radius,
+        )
+    };
+
+    let mut previous = point(start);
+    for i in 1..=segments {
+        let next = point(start + step * i as f32);
+        push_triangle(triangles, center, previous, next);
+        previous = next;
+    }
+}
+
+/// Appends a single triangle.
+pub(crate) fn push_triangle(triangles: &mut Vec<f32>, a: (f32, f32), b: (f32, f32), c: (f32, f32)) {
+    triangles.extend_from_slice(&[a.0, a.1, b.0, b.1, c.0, c.1]);
+}
+
+fn add(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: (f32, f32), factor: f32) -> (f32, f32) {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn dot(a: (f32, f32), b: (f32, f32)) -> f32 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+/// Returns the unit direction from one point to another.
+fn direction(from: (f32, f32), to: (f32, f32)) -> (f32, f32) {
+    let delta = sub(to, from);
+    let length = dot(delta, delta).sqrt();
+    scale(delta, 1.0 / length)
+}
+
+/// Returns a direction rotated by 90 degrees.
+fn normal(direction: (f32, f32)) -> (f32, f32) {
+    (-direction.1, direction.0)
+}
+
+/// Appends the triangles joining two segments at a point.
+fn push_join(
+    triangles: &mut Vec<f32>,
+    point: (f32, f32),
+    incoming: (f32, f32),
+    outgoing: (f32, f32),
+    half_width: f32,
+    join: LineJoin,
+) {
+    let normal_in = normal(incoming);
+    let normal_out = normal(outgoing);
+
+    // Straight on; the segments already meet
+    if dot(incoming, outgoing) > 0.9999 {
+        return;
+    }
+
+    // The gap to fill is on the outside of the turn
+    let side = if dot(outgoing, normal_in) > 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+
+    let from = add(point, scale(normal_in, half_width * side));
+    let to = add(point, scale(normal_out, half_width * side));
+
+    match join {
+        LineJoin::Bevel => push_triangle(triangles, point, from, to),
+        LineJoin::Round => {
+            let start = (from.1 - point.1).atan2(from.0 - point.0);
+            let mut end = (to.1 - point.1).atan2(to.0 - point.0);
+
+            // Take the short way around
+            if end - start > PI {
+                end -= 2.0 * PI;
+            } else if end - start < -PI {
+                end += 2.0 * PI;
+            }
+
+            push_arc(triangles, point, half_width, start, end);
+        }
+        LineJoin::Miter => {
+            let bisector = add(normal_in, normal_out);
+            let length = dot(bisector, bisector).sqrt();
+            let cos_half_angle = length / 2.0;
+
+            if length < 1e-4 || 1.0 / cos_half_angle > MITER_LIMIT {
+                push_triangle(triangles, point, from, to);
+                return;
+            }
+
+            let miter = add(
+                point,
+                scale(bisector, side * half_width / (length * cos_half_angle)),
+            );
+
+            push_triangle(triangles, point, from, miter);
+            push_triangle(triangles, point, miter, to);
+        }
+    }
+}
+
+/// Tessellates a polyline into a list of triangles (x/y pairs, 3 points per triangle), in
+/// the same coordinates as the input points. Closed polylines have a join between their
+/// last and first points, instead of caps.
+pub(crate) fn tessellate_polyline(
+    points: &[(f32, f32)],
+    closed: bool,
+    width: f32,
+    join: LineJoin,
+    cap: LineCap,
+) -> Vec<f32> {
+    let mut triangles = Vec::new();
+
+    if width <= 0.0 {
+        return triangles;
+    }
+
+    let half_width = width / 2.0;
+
+    // Repeated points have no direction
+    let mut path: Vec<(f32, f32)> = Vec::with_capacity(points.len());
+    for &point in points {
+        if path.last() != Some(&point) {
+            path.push(point);
+        }
+    }
+
+    if closed && path.len() > 1 && path.first() == path.last() {
+        path.pop();
+    }
+
+    match path.len() {
+        0 => return triangles,
+        1 => {
+            // A single point only shows up if it has a cap
+            let point = path[0];
+            match cap {
+                LineCap::Butt => {}
+                LineCap::Round => push_arc(&mut triangles, point, half_width, 0.0, 2.0 * PI),
+                LineCap::Square => {
+                    let top_left = sub(point, (half_width, half_width));
+                    let bottom_right = add(point, (half_width, half_width));
+                    let top_right = (bottom_right.0, top_left.1);
+                    let bottom_left = (top_left.0, bottom_right.1);
+                    push_triangle(&mut triangles, top_left, top_right, bottom_right);
+                    push_triangle(&mut triangles, top_left, bottom_right, bottom_left);
+                }
+            }
+            return triangles;
+        }
+        _ => {}
+    }
+
+    if closed {
+        // Go back around to the start, and join the last segment to the first
+        path.push(path[0]);
+        push_join(
+            &mut triangles,
+            path[0],
+            direction(path[path.len() - 2], path[0]),
+            direction(path[0], path[1]),
+            half_width,
+            join,
+        );
+    }
+
+    let last = path.len() - 1;
+    let start_direction = direction(path[0], path[1]);
+    let end_direction = direction(path[last - 1], path[last]);
+
+    // Square caps are the same as extending the line
+    if !closed && cap == LineCap::Square {
+        path[0] = sub(path[0], scale(start_direction, half_width));
+        path[last] = add(path[last], scale(end_direction, half_width));
+    }
+
+    for i in 0..last {
+        let from = path[i];
+        let to = path[i + 1];
+        let segment_direction = direction(from, to);
+        let offset = scale(normal(segment_direction), half_width);
+
+        let a = add(from, offset);
+        let b = sub(from, offset);
+        let c = sub(to, offset);
+        let d = add(to, offset);
+
+        push_triangle(&mut triangles, a, b, c);
+        push_triangle(&mut triangles, a, c, d);
+
+        if i + 1 < last {
+            let next_direction = direction(to, path[i + 2]);
+            push_join(
+                &mut triangles,
+                to,
+                segment_direction,
+                next_direction,
+                half_width,
+                join,
+            );
+        }
+    }
+
+    if !closed && cap == LineCap::Round {
+        let start_angle = start_direction.1.atan2(start_direction.0);
+        let end_angle = end_direction.1.atan2(end_direction.0);
+
+        push_arc(
+            &mut triangles,
+            path[0],
+            half_width,
+            start_angle + PI / 2.0,
+            start_angle + PI * 1.5,
+        );
+        push_arc(
+            &mut triangles,
+            path[last],
+            half_width,
+            end_angle - PI / 2.0,
+            end_angle + PI / 2.0,
+        );
+    }
+
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arc_segments_are_bounded() {
+        assert_eq!(arc_segments(1e9, 2.0 * PI), 128);
+        assert_eq!(arc_segments(1e9, 1e9), 128);
+        assert_eq!(arc_segments(1e9, -f32::INFINITY), 128);
+        assert_eq!(arc_segments(1e9, f32::NAN), 1);
+        assert_eq!(arc_segments(f32::NAN, PI), 1);
+        assert_eq!(arc_segments(1.0, PI), 6);
+    }
+}
@@ -1,4 +1,19 @@
+pub mod atlas;
+mod clip;
+pub mod config;
+pub mod display_list;
 pub mod font;
+pub mod gradient;
+pub mod line;
+pub mod path;
+pub mod region;
+mod shapes;
+pub mod sprite;
+pub mod target;
+pub mod transform;
+
+#[cfg(any(feature = "desktop_gl", feature = "raspberry_pi"))]
+mod batch;
 
 #[cfg(feature = "desktop_gl")]
 pub mod glutin;
@@ -10,14 +25,41 @@ pub mod pi;
 #[cfg(feature = "raspberry_pi")]
 pub use self::pi::drawer::PiDrawer as drawer_impl;
 
+#[cfg(feature = "software")]
+pub mod software;
+#[cfg(all(
+    feature = "software",
+    not(any(feature = "desktop_gl", feature = "raspberry_pi"))
+))]
+pub use self::software::drawer::SoftwareDrawer as drawer_impl;
+
+use crate::pos::Insets;
 use crate::pos::Position;
 use crate::pos::Rect;
 
+use crate::render::atlas::AtlasRegion;
+use crate::render::config::DrawerConfig;
+use crate::render::gradient::Gradient;
+use crate::render::line::LineCap;
+use crate::render::line::LineJoin;
+use crate::render::path::FillRule;
+use crate::render::path::Path;
+use crate::render::region::TextureRegion;
+use crate::render::target::RenderTarget;
+use crate::render::transform::Transform;
+use crate::Error;
+
 use image::DynamicImage;
+use image::GrayImage;
+use image::RgbImage;
+
 use image::RgbaImage;
+use std::f32::consts::PI;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 
 /// Represents a unsigned OpenGL color in Rust form.
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -42,22 +84,246 @@ impl Color {
     }
 }
 
+/// How drawn colors are combined with what has already been drawn.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum BlendMode {
+    /// Colors replace what was there before, including alpha.
+    None,
+    /// Standard transparency, for colors which aren't premultiplied.
+    /// (`SRC_ALPHA`, `ONE_MINUS_SRC_ALPHA`)
+    Alpha,
+    /// Standard transparency, for colors which have already been multiplied by their alpha.
+    /// (`ONE`, `ONE_MINUS_SRC_ALPHA`)
+    PremultipliedAlpha,
+    /// Colors are added to what was there before, weighted by their alpha. Useful for glows
+    /// and lights. (`SRC_ALPHA`, `ONE`)
+    Additive,
+    /// Colors are multiplied with what was there before, darkening it. Alpha is ignored,
+    /// unless the premultiplied alpha pipeline is enabled. (`DST_COLOR`, `ZERO`)
+    Multiply,
+    /// The inverse of colors are multiplied with the inverse of what was there before,
+    /// lightening it. Alpha is ignored. (`ONE`, `ONE_MINUS_SRC_COLOR`)
+    Screen,
+}
+
+/// How texels are blended together when a texture is drawn at a different size.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum TextureFilter {
+    /// The closest texel is used, keeping edges crisp. Best for pixel art.
+    Nearest,
+    /// The four closest texels are blended together.
+    Linear,
+    /// The four closest texels are blended together on the two closest mipmap levels, and
+    /// then between those levels. Always generates mipmaps.
+    Trilinear,
+}
+
+/// What is drawn when texture coordinates fall outside of the texture.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum TextureWrap {
+    /// The edge of the texture is stretched outwards.
+    Clamp,
+    /// The texture is tiled.
+    Repeat,
+    /// The texture is tiled, with every other tile mirrored so that the edges line up.
+    Mirror,
+}
+
+/// How the pixels of a texture are laid out in memory. Smaller formats save memory on the
+/// GPU, which is scarce on the Raspberry Pi.
+///
+/// 16-bit formats store each pixel as a `u16` in native byte order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum PixelFormat {
+    /// 8 bits each of red, green, blue and alpha.
+    Rgba8888,
+    /// 8 bits each of red, green and blue. Always opaque.
+    Rgb888,
+    /// 5 bits of red, 6 of green and 5 of blue. Always opaque.
+    Rgb565,
+    /// 4 bits each of red, green, blue and alpha.
+    Rgba4444,
+    /// 8 bits of alpha, with white as the color. Suited to glyphs and masks, which are
+    /// tinted by the color they are drawn with.
+    Alpha8,
+    /// 8 bits of brightness. Always opaque.
+    Luminance8,
+}
+
+impl PixelFormat {
+    /// Returns how many bytes each pixel takes up.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8888 => 4,
+            PixelFormat::Rgb888 => 3,
+            PixelFormat::Rgb565 | PixelFormat::Rgba4444 => 2,
+            PixelFormat::Alpha8 | PixelFormat::Luminance8 => 1,
+        }
+    }
+
+    /// Checks if pixels in this format have both a color and an alpha, and so can be
+    /// premultiplied.
+    pub fn can_premultiply(self) -> bool {
+        self == PixelFormat::Rgba8888 || self == PixelFormat::Rgba4444
+    }
+
+    /// Writes a color into a single pixel of this format.
+    pub(crate) fn encode(self, color: &Color, pixel: &mut [u8]) {
+        match self {
+            PixelFormat::Rgba8888 => {
+                pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+            PixelFormat::Rgb888 => pixel.copy_from_slice(&[color.r, color.g, color.b]),
+            PixelFormat::Rgb565 => {
+                let value = (u16::from(color.r) >> 3) << 11
+                    | (u16::from(color.g) >> 2) << 5
+                    | u16::from(color.b) >> 3;
+                pixel.copy_from_slice(&value.to_ne_bytes());
+            }
+            PixelFormat::Rgba4444 => {
+                let value = (u16::from(color.r) >> 4) << 12
+                    | (u16::from(color.g) >> 4) << 8
+                    | (u16::from(color.b) >> 4) << 4
+                    | u16::from(color.a) >> 4;
+                pixel.copy_from_slice(&value.to_ne_bytes());
+            }
+            PixelFormat::Alpha8 => pixel[0] = color.a,
+            PixelFormat::Luminance8 => {
+                // Rec. 601 luma, as used by `image`
+                let luma = (u32::from(color.r) * 299
+                    + u32::from(color.g) * 587
+                    + u32::from(color.b) * 114
+                    + 500)
+                    / 1000;
+                pixel[0] = luma as u8;
+            }
+        }
+    }
+
+    /// Reads a single pixel of this format as a color.
+    pub(crate) fn decode(self, pixel: &[u8]) -> Color {
+        // Scales a value with the specified number of bits up to 8 bits
+        let expand = |value: u16, bits: u32| {
+            let max = (1u32 << bits) - 1;
+            ((u32::from(value) * 255 + max / 2) / max) as u8
+        };
+
+        match self {
+            PixelFormat::Rgba8888 => Color::new_4byte(pixel[0], pixel[1], pixel[2], pixel[3]),
+            PixelFormat::Rgb888 => Color::new_3byte(pixel[0], pixel[1], pixel[2]),
+            PixelFormat::Rgb565 => {
+                let value = u16::from_ne_bytes([pixel[0], pixel[1]]);
+                Color::new_3byte(
+                    expand(value >> 11, 5),
+                    expand((value >> 5) & 0x3F, 6),
+                    expand(value & 0x1F, 5),
+                )
+            }
+            PixelFormat::Rgba4444 => {
+                let value = u16::from_ne_bytes([pixel[0], pixel[1]]);
+                Color::new_4byte(
+                    expand(value >> 12, 4),
+                    expand((value >> 8) & 0xF, 4),
+                    expand((value >> 4) & 0xF, 4),
+                    expand(value & 0xF, 4),
+                )
+            }
+            PixelFormat::Alpha8 => Color::new_4byte(255, 255, 255, pixel[0]),
+            PixelFormat::Luminance8 => Color::new_3byte(pixel[0], pixel[0], pixel[0]),
+        }
+    }
+}
+
+/// Converts a byte array of pixels from one format to another.
+pub(crate) fn convert_pixels(bytes: &[u8], from: PixelFormat, to: PixelFormat) -> Vec<u8> {
+    if from == to {
+        return bytes.to_vec();
+    }
+
+    let mut result = vec![0; bytes.len() / from.bytes_per_pixel() * to.bytes_per_pixel()];
+    for (src, dest) in bytes
+        .chunks(from.bytes_per_pixel())
+        .zip(result.chunks_mut(to.bytes_per_pixel()))
+    {
+        to.encode(&from.decode(src), dest);
+    }
+
+    result
+}
+
+/// Controls how a texture is sampled when drawn.
+///
+/// On the Raspberry Pi, textures which aren't a power of two in size are always clamped,
+/// and never have mipmaps.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct TextureOptions {
+    pub filter: TextureFilter,
+    pub wrap: TextureWrap,
+    /// Generates smaller copies of the texture, which are used when it is drawn smaller
+    /// than its actual size to avoid shimmering.
+    pub mipmaps: bool,
+}
+
+impl TextureOptions {
+    /// Checks if mipmaps need to be generated for these options.
+    pub fn needs_mipmaps(&self) -> bool {
+        self.mipmaps || self.filter == TextureFilter::Trilinear
+    }
+}
+
+impl Default for TextureOptions {
+    /// Linear filtering, clamped to the edges, without mipmaps.
+    fn default() -> Self {
+        TextureOptions {
+            filter: TextureFilter::Linear,
+            wrap: TextureWrap::Clamp,
+            mipmaps: false,
+        }
+    }
+}
+
 /// Handles textures, and provides mechanisms for interacting/drawing on them
 /// safely.
+#[derive(Clone)]
 pub struct Texture {
     pub tex_data: Vec<u8>,
     width: usize,
     height: usize,
+    format: PixelFormat,
 }
 
 impl Texture {
+    /// Sets a single pixel, converting the color to the format of this texture.
     pub fn draw_pixel(&mut self, color: &Color, x: usize, y: usize) {
-        let starting_pos = (y * self.width + x) * 4;
+        let size = self.format.bytes_per_pixel();
+        let starting_pos = (y * self.width + x) * size;
+
+        self.format
+            .encode(color, &mut self.tex_data[starting_pos..starting_pos + size]);
+    }
+
+    /// Returns the color of a single pixel.
+    pub fn get_pixel(&self, x: usize, y: usize) -> Color {
+        let size = self.format.bytes_per_pixel();
+        let starting_pos = (y * self.width + x) * size;
+
+        self.format
+            .decode(&self.tex_data[starting_pos..starting_pos + size])
+    }
+
+    /// Returns the layout of the pixels in this texture.
+    pub fn get_format(&self) -> PixelFormat {
+        self.format
+    }
 
-        self.tex_data[starting_pos] = color.r;
-        self.tex_data[starting_pos + 1] = color.g;
-        self.tex_data[starting_pos + 2] = color.b;
-        self.tex_data[starting_pos + 3] = color.a;
+    /// Creates a copy of this texture, with its pixels converted to another format.
+    pub fn to_format(&self, format: PixelFormat) -> Texture {
+        Texture {
+            tex_data: convert_pixels(&self.tex_data, self.format, format),
+            width: self.width,
+            height: self.height,
+            format,
+        }
     }
 
     pub fn get_width(&self) -> usize {
@@ -70,12 +336,176 @@ impl Texture {
 
     /// Creates a new Texture for drawing. This is only uploaded on demand.
     pub fn new(width: usize, height: usize) -> Self {
+        Texture::new_with_format(width, height, PixelFormat::Rgba8888)
+    }
+
+    /// Creates a new Texture for drawing, with pixels in the specified format.
+    pub fn new_with_format(width: usize, height: usize, format: PixelFormat) -> Self {
         Texture {
-            tex_data: vec![0; width * height * 4],
+            tex_data: vec![0; width * height * format.bytes_per_pixel()],
+            width,
+            height,
+            format,
+        }
+    }
+
+    /// Wraps existing pixel data in the specified format. Fails if there isn't exactly
+    /// enough data for a texture of this size.
+    pub fn from_data(
+        tex_data: Vec<u8>,
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+    ) -> Result<Self, Error> {
+        let expected = width * height * format.bytes_per_pixel();
+        if tex_data.len() != expected {
+            return Err(Error::Texture(format!(
+                "Expected {} bytes of {:?} data for a {}x{} texture, got {}",
+                expected,
+                format,
+                width,
+                height,
+                tex_data.len()
+            )));
+        }
+
+        Ok(Texture {
+            tex_data,
             width,
             height,
+            format,
+        })
+    }
+
+    /// Uses a greyscale image as the alpha of an otherwise white texture, such as a mask
+    /// or pre-rendered glyphs.
+    pub fn from_alpha_image(image: GrayImage) -> Self {
+        Texture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            tex_data: image.into_raw(),
+            format: PixelFormat::Alpha8,
+        }
+    }
+}
+
+impl From<RgbaImage> for Texture {
+    /// Wraps a RGBA image as a `Rgba8888` texture.
+    fn from(image: RgbaImage) -> Self {
+        Texture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            tex_data: image.into_raw(),
+            format: PixelFormat::Rgba8888,
+        }
+    }
+}
+
+impl From<RgbImage> for Texture {
+    /// Wraps a RGB image as a `Rgb888` texture.
+    fn from(image: RgbImage) -> Self {
+        Texture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            tex_data: image.into_raw(),
+            format: PixelFormat::Rgb888,
+        }
+    }
+}
+
+impl From<GrayImage> for Texture {
+    /// Wraps a greyscale image as a `Luminance8` texture. Use `Texture::from_alpha_image`
+    /// for masks instead.
+    fn from(image: GrayImage) -> Self {
+        Texture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            tex_data: image.into_raw(),
+            format: PixelFormat::Luminance8,
+        }
+    }
+}
+
+/// Multiplies the color channels of a RGBA byte array by their alpha.
+pub(crate) fn premultiply_alpha(bytes: &[u8]) -> Vec<u8> {
+    let mut result = bytes.to_vec();
+
+    for pixel in result.chunks_mut(4) {
+        let alpha = u16::from(pixel[3]);
+        for channel in &mut pixel[0..3] {
+            *channel = ((u16::from(*channel) * alpha + 127) / 255) as u8;
         }
     }
+
+    result
+}
+
+/// Multiplies the color channels of a byte array of pixels in the specified format by their
+/// alpha. Formats without both color and alpha are left as they are.
+#[cfg(any(feature = "desktop_gl", feature = "raspberry_pi"))]
+pub(crate) fn premultiply_pixels(bytes: &[u8], format: PixelFormat) -> Vec<u8> {
+    match format {
+        PixelFormat::Rgba8888 => premultiply_alpha(bytes),
+        PixelFormat::Rgba4444 => {
+            let rgba = convert_pixels(bytes, format, PixelFormat::Rgba8888);
+            convert_pixels(&premultiply_alpha(&rgba), PixelFormat::Rgba8888, format)
+        }
+        _ => bytes.to_vec(),
+    }
+}
+
+/// Repeats a color as RGBA values from 0 to 1, once for each of the specified number of
+/// points.
+pub(crate) fn repeat_color(color: &Color, count: usize) -> Vec<f32> {
+    let color = [
+        f32::from(color.r) / 255.0,
+        f32::from(color.g) / 255.0,
+        f32::from(color.b) / 255.0,
+        f32::from(color.a) / 255.0,
+    ];
+
+    let mut colors = Vec::with_capacity(count * 4);
+    for _ in 0..count {
+        colors.extend_from_slice(&color);
+    }
+
+    colors
+}
+
+/// Checks that a region lies within a texture of the specified size, and that there is
+/// exactly enough data in the specified format to fill it.
+pub(crate) fn check_texture_update(
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    region: &Rect,
+    data: &[u8],
+) -> Result<(), Error> {
+    if region.x < 0
+        || region.y < 0
+        || region.width < 0
+        || region.height < 0
+        || (region.x + region.width) as usize > width
+        || (region.y + region.height) as usize > height
+    {
+        return Err(Error::Texture(format!(
+            "Region {:?} lies outside of the {}x{} texture",
+            region, width, height
+        )));
+    }
+
+    let expected = region.width as usize * region.height as usize * format.bytes_per_pixel();
+    if data.len() != expected {
+        return Err(Error::Texture(format!(
+            "Expected {} bytes of {:?} data for region {:?}, got {}",
+            expected,
+            format,
+            region,
+            data.len()
+        )));
+    }
+
+    Ok(())
 }
 
 /// The dimensions of a object
@@ -87,6 +517,18 @@ pub trait Dimensions {
     fn get_height(&self) -> usize;
 }
 
+/// Textures created by a drawer, which can be told apart however they are moved around.
+pub trait UniqueTexture: Dimensions {
+    /// Returns an ID which no other texture has been given while this program is running.
+    fn get_unique_id(&self) -> u64;
+}
+
+/// Hands out the IDs returned by `UniqueTexture::get_unique_id`.
+pub(crate) fn next_texture_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
 /// Structures for rendering stuff to the screen
 pub trait Drawer {
     type NativeTexture: Sized + Dimensions;
@@ -95,34 +537,127 @@ pub trait Drawer {
     fn start(&mut self);
 
     /// Ends a frame, requesting for framebuffers to be finalised/etc
-    fn end(&mut self);
+    ///
+    /// Fails if the frame couldn't be presented, such as when the graphics context has been
+    /// lost.
+    fn end(&mut self) -> Result<(), Error>;
 
-    /// Clears the frame.
-    /// transparent: If the frame should be cleared to alpha 0.
+    /// Clears the frame (or the current render target), within the current clip.
+    /// transparent: If the frame should be cleared to alpha 0. On the screen, the background
+    /// (see `set_background`) is then shown underneath; render targets are left transparent.
     fn clear(&mut self, transparent: bool);
 
     /// Enables blending of a texture/etc with the background, if this is
-    ///  explicitly required.
-    fn enable_blending(&mut self);
+    ///  explicitly required. This is the same as setting `BlendMode::Alpha`.
+    fn enable_blending(&mut self) {
+        self.set_blend_mode(BlendMode::Alpha)
+    }
+
+    /// Changes how following draws are combined with what has already been drawn.
+    ///
+    /// Blending starts each frame as `BlendMode::None`, and is reset to it at the end of
+    /// each frame.
+    fn set_blend_mode(&mut self, mode: BlendMode);
+
+    /// Returns how draws are currently combined with what has already been drawn.
+    fn get_blend_mode(&self) -> BlendMode;
+
+    /// Switches to a premultiplied alpha pipeline. Textures converted while this is enabled
+    /// have their colors multiplied by their alpha, which stops transparent edges from
+    /// showing dark fringes when they are scaled. Draws are then output premultiplied, with
+    /// blend modes adjusted to match, so they look the same as before.
+    ///
+    /// This is best enabled before any textures are converted. Textures converted
+    /// beforehand are still drawn correctly, but keep their fringes.
+    fn set_premultiplied_alpha(&mut self, enabled: bool);
+
+    /// Checks if the premultiplied alpha pipeline is enabled.
+    fn is_premultiplied_alpha(&self) -> bool;
+
+    /// Submits any draws which have been batched up, but not yet sent to the GPU.
+    ///
+    /// Textures which are dropped while draws using them are still batched are kept until
+    /// those draws have been submitted here.
+    fn flush(&mut self) {}
 
     /// Converts an RGBA image to a native image.
-    fn convert_image(&mut self, texture: &RgbaImage) -> Self::NativeTexture;
+    fn convert_image(&mut self, texture: &RgbaImage) -> Self::NativeTexture {
+        self.convert_image_with_options(texture, &TextureOptions::default())
+    }
 
     /// Converts a texture to a native reference.
-    fn convert_native_texture(&mut self, texture: Texture) -> Self::NativeTexture;
+    fn convert_native_texture(&mut self, texture: Texture) -> Self::NativeTexture {
+        self.convert_native_texture_with_options(texture, &TextureOptions::default())
+    }
+
+    /// Converts an RGBA image to a native image, which is sampled using the specified
+    /// options.
+    fn convert_image_with_options(
+        &mut self,
+        texture: &RgbaImage,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture;
+
+    /// Converts a texture to a native reference, which is sampled using the specified
+    /// options.
+    fn convert_native_texture_with_options(
+        &mut self,
+        texture: Texture,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture;
 
-    /// Returns the width of the framebuffer.
+    /// Replaces the pixels in a region of a texture with new data, without creating a new
+    /// texture. This is much cheaper than converting a new texture each frame for content
+    /// which changes often, such as video or camera previews.
+    ///
+    /// The data must be in the format the texture was converted from, and must exactly fill
+    /// the region, which must lie within the texture. Batched
+    /// draws are flushed first, so they still see the old contents.
+    fn update_texture(
+        &mut self,
+        texture: &mut Self::NativeTexture,
+        region: &Rect,
+        data: &[u8],
+    ) -> Result<(), Error>;
+
+    /// Changes the size of a texture in place, keeping its options. The contents become
+    /// fully transparent. Textures which are the current render target must not be resized.
+    fn resize_texture(&mut self, texture: &mut Self::NativeTexture, width: usize, height: usize);
+
+    /// Returns the width of the framebuffer, or the current render target.
+    ///
+    /// The size of the screen is read at the start of each frame, and stays the same until
+    /// the next one even if the window is resized in the meantime. See `Input::get_resize`
+    /// to be told when it changes.
     fn get_width(&self) -> usize;
 
-    /// Returns the height of the framebuffer.
+    /// Returns the height of the framebuffer, or the current render target.
     fn get_height(&self) -> usize;
 
+    /// Returns how many physical pixels make up one logical unit on the monitor being drawn
+    /// to, such as 2.0 on most "Retina" displays. This may change if the window moves to
+    /// another monitor. Platforms without scaling always return 1.0.
+    ///
+    /// The width and height of the screen are in logical units if the drawer was created
+    /// with `DrawerConfig::with_logical_units`, and in pixels otherwise.
+    fn scale_factor(&self) -> f64 {
+        1.0
+    }
+
+    /// Checks if draws are in logical units rather than physical pixels. See
+    /// `DrawerConfig::with_logical_units`.
+    fn uses_logical_units(&self) -> bool {
+        false
+    }
+
     /// Uses the specified image as a background. This is provided as several platforms
     /// have ways to accelerate this beyond OpenGL calls.
+    ///
+    /// Headless drawers on the Pi have nowhere to show a background, so ignore it.
     fn set_background(&mut self, image: DynamicImage);
 
     /// Sets the screen brightness, if possible. Ignore call if not.
-    fn set_brightness(&mut self, brightness: u8) -> ::std::io::Result<()>;
+    fn set_brightness(&mut self, brightness: u8) -> Result<(), Error>;
 
     /// Draws a texture to the screen, with a specified set of vertices to draw to, a UV
     /// to decode the image with, and a color to use as a base.
@@ -140,6 +675,55 @@ pub trait Drawer {
     /// Returns the count of transitions that occured so far in this frame.
     fn get_transition_count(&self) -> usize;
 
+    /// Combines the specified transform with the current one. It is applied to all position
+    /// and rect based draws (but not raw vertices) until it is popped again.
+    ///
+    /// The stack is emptied at the start of each frame.
+    fn push_transform(&mut self, transform: &Transform);
+
+    /// Restores the transform which was in effect before the last push.
+    fn pop_transform(&mut self);
+
+    /// Returns the transform which is currently applied to draws.
+    fn get_transform(&self) -> Transform;
+
+    /// Restricts all following draws and clears to the specified rectangle, intersected
+    /// with the current clip, until it is popped again. The rectangle is moved by the
+    /// current transform; if it is rotated, the box around it is used instead.
+    ///
+    /// The stack is emptied at the start of each frame.
+    fn push_clip(&mut self, rect: &Rect);
+
+    /// Restores the clip which was in effect before the last push.
+    fn pop_clip(&mut self);
+
+    /// Returns the area, in the same units as `get_width` and `get_height`, which draws are
+    /// currently restricted to. None if the whole screen may be drawn to.
+    fn get_clip(&self) -> Option<Rect>;
+
+    /// Creates a new, fully transparent texture of the specified size, which can be drawn
+    /// into using `set_render_target`.
+    fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderTarget<Self::NativeTexture>, Error>;
+
+    /// Redirects all following draws into the specified target, or back to the screen if
+    /// None. Clears only affect the target (and never draw the background), and
+    /// `get_width`, `get_height` and `capture` refer to the target while it is set. The
+    /// transform and clip stacks are left as they are.
+    ///
+    /// The target must stay alive until it is unset, which happens automatically at the
+    /// end of the frame.
+    fn set_render_target(&mut self, target: Option<&RenderTarget<Self::NativeTexture>>);
+
+    /// Reads back what has been drawn to the framebuffer so far in this frame.
+    ///
+    /// This should be called before `end()`, as some platforms discard the framebuffer
+    /// once it has been presented.
+    fn capture(&mut self) -> RgbaImage;
+
     /// Draws a texture to the screen, with a specified set of vertices to draw to, and a color
     /// to use as a base.
     fn draw_textured_vertices_colored(
@@ -204,9 +788,130 @@ pub trait Drawer {
         self.draw_texture_colored(texture, pos, &Color::new_4byte(255, 255, 255, 255))
     }
 
+    /// Draws a region of a texture (in texels) to the screen, stretched over the specified
+    /// rectangle, and flipped if the region asks for it.
+    fn draw_texture_region(
+        &mut self,
+        texture: &Self::NativeTexture,
+        region: &TextureRegion,
+        rect: &Rect,
+        color: &Color,
+    ) {
+        let vertices = self.rect_to_vertices(rect);
+
+        let colors = repeat_color(color, vertices.len() / 2);
+
+        let uv = region.get_uv(texture.get_width(), texture.get_height());
+        self.draw_textured_vertices_colored_uv(texture, &vertices, &colors, &uv)
+    }
+
+    /// Draws a texture stretched over a rectangle, keeping the corners (as marked out by the
+    /// insets, in texture pixels) at their original size. The edges are only stretched along
+    /// their length, and the center fills the rest. If the rectangle is too small to fit the
+    /// corners, they are shrunk to fit.
+    fn draw_nine_slice(
+        &mut self,
+        texture: &Self::NativeTexture,
+        insets: &Insets,
+        rect: &Rect,
+        color: &Color,
+    ) {
+        let texture_width = texture.get_width() as f32;
+        let texture_height = texture.get_height() as f32;
+
+        // Splits a span into its start, middle and end, for both the screen and the texture
+        let split = |start: i32, length: i32, before: i32, after: i32, size: f32| {
+            let before = before.max(0);
+            let after = after.max(0);
+
+            let (before_length, after_length) = if before + after > length {
+                let shrunk = before * length.max(0) / (before + after).max(1);
+                (shrunk, length.max(0) - shrunk)
+            } else {
+                (before, after)
+            };
+
+            let positions = [
+                start,
+                start + before_length,
+                start + length - after_length,
+                start + length,
+            ];
+            let coords = [0.0, before as f32 / size, 1.0 - after as f32 / size, 1.0];
+
+            (positions, coords)
+        };
+
+        let (xs, us) = split(rect.x, rect.width, insets.left, insets.right, texture_width);
+        let (ys, vs) = split(
+            rect.y,
+            rect.height,
+            insets.top,
+            insets.bottom,
+            texture_height,
+        );
+
+        // Each slice is drawn as two triangles
+        let colors = repeat_color(color, 6);
+
+        for row in 0..3 {
+            for column in 0..3 {
+                let slice = Rect::new(
+                    xs[column],
+                    ys[row],
+                    xs[column + 1] - xs[column],
+                    ys[row + 1] - ys[row],
+                );
+
+                if slice.width <= 0 || slice.height <= 0 {
+                    continue;
+                }
+
+                let (min_u, max_u) = (us[column], us[column + 1]);
+                let (min_v, max_v) = (vs[row], vs[row + 1]);
+
+                let uv = [
+                    min_u, min_v, min_u, max_v, max_u, max_v, // Triangle 1
+                    min_u, min_v, max_u, min_v, max_u, max_v,
+                ];
+
+                let vertices = self.rect_to_vertices(&slice);
+                self.draw_textured_vertices_colored_uv(texture, &vertices, &colors, &uv);
+            }
+        }
+    }
+
+    /// Draws a region of a texture atlas to the screen, with the specified x/y coordinates
+    /// (relative to screen size), and a specified width/height. The texture must be the one
+    /// returned by the atlas or sprite sheet the region came from.
+    fn draw_region_sized(
+        &mut self,
+        texture: &Self::NativeTexture,
+        region: &AtlasRegion,
+        rect: &Rect,
+        color: &Color,
+    ) {
+        let vertices = self.rect_to_vertices(rect);
+
+        let colors = repeat_color(color, vertices.len() / 2);
+
+        self.draw_textured_vertices_colored_uv(texture, &vertices, &colors, &region.get_uv())
+    }
+
+    /// Draws a region of a texture atlas to the screen, with the specified x/y coordinates
+    /// (relative to screen size), and the region dimensions as width/height.
+    fn draw_region(&mut self, texture: &Self::NativeTexture, region: &AtlasRegion, pos: &Position) {
+        self.draw_region_sized(
+            texture,
+            region,
+            &Rect::new_from_pos(pos, region.width as i32, region.height as i32),
+            &Color::new_4byte(255, 255, 255, 255),
+        )
+    }
+
     /// Draws a colored rectangle to the screen, with a single color.
     fn draw_colored_rect(&mut self, rect: &Rect, color: &Color) {
-        let vertices: [f32; 12] = self.rect_to_vertices(&rect);
+        let vertices: [f32; 12] = self.rect_to_vertices(rect);
         let mut colors: [f32; 24] = [0.0; 24];
 
         for i in 0..24 / 4 {
@@ -219,27 +924,466 @@ pub trait Drawer {
         self.draw_colored_vertices(&vertices, &colors)
     }
 
-    /// Converts a rectangle to 4 vertices
+    /// Draws a list of triangles, with a color for each point. Points are in pixels, as
+    /// x/y pairs with 3 points per triangle, and have the current transform applied.
+    /// Colors are RGBA, from 0 to 1.
+    fn draw_colored_triangles(&mut self, points: &[f32], colors: &[f32]) {
+        let count = (points.len() / 2).min(colors.len() / 4) / 3 * 3;
+
+        let mut vertices = Vec::with_capacity(count * 2 * 5 / 3);
+        let mut strip_colors = Vec::with_capacity(count * 4 * 5 / 3);
+
+        // Join the triangles into a single strip, by repeating the first and last point of
+        // each triangle to form degenerate triangles between them
+        for i in 0..count {
+            let vertex = self.pixel_to_vertex(points[i * 2], points[i * 2 + 1]);
+            let color = &colors[i * 4..i * 4 + 4];
+
+            let repeat = (i % 3 == 0 && i > 0) || (i % 3 == 2 && i + 1 < count);
+            let copies = if repeat { 2 } else { 1 };
+
+            for _ in 0..copies {
+                vertices.extend_from_slice(&vertex);
+                strip_colors.extend_from_slice(color);
+            }
+        }
+
+        self.draw_colored_vertices(&vertices, &strip_colors)
+    }
+
+    /// Draws a line between two points, with the specified width in pixels.
+    fn draw_line(&mut self, from: &Position, to: &Position, width: f32, color: &Color) {
+        self.draw_polyline(&[*from, *to], width, color, LineJoin::Miter, LineCap::Butt)
+    }
+
+    /// Draws a line through a series of points, with the specified width in pixels, and
+    /// the specified joins between segments and caps on either end.
+    fn draw_polyline(
+        &mut self,
+        points: &[Position],
+        width: f32,
+        color: &Color,
+        join: LineJoin,
+        cap: LineCap,
+    ) {
+        let points: Vec<(f32, f32)> = points
+            .iter()
+            .map(|point| (point.x as f32, point.y as f32))
+            .collect();
+
+        let triangles = line::tessellate_polyline(&points, false, width, join, cap);
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws a filled circle, with the specified radius in pixels.
+    fn draw_circle(&mut self, center: &Position, radius: f32, color: &Color) {
+        self.draw_ellipse(center, radius, radius, color)
+    }
+
+    /// Draws the outline of a circle, with the specified radius and line width in pixels.
+    /// The line is centered on the edge of the circle.
+    fn draw_circle_outline(&mut self, center: &Position, radius: f32, width: f32, color: &Color) {
+        self.draw_ellipse_outline(center, radius, radius, width, color)
+    }
+
+    /// Draws a filled ellipse, with the specified horizontal and vertical radii in pixels.
+    fn draw_ellipse(&mut self, center: &Position, radius_x: f32, radius_y: f32, color: &Color) {
+        let triangles = shapes::fill_ellipse(
+            (center.x as f32, center.y as f32),
+            radius_x,
+            radius_y,
+            self.get_transform().max_scale(),
+        );
+
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws the outline of an ellipse, with the specified horizontal and vertical radii and
+    /// line width in pixels. The line is centered on the edge of the ellipse.
+    fn draw_ellipse_outline(
+        &mut self,
+        center: &Position,
+        radius_x: f32,
+        radius_y: f32,
+        width: f32,
+        color: &Color,
+    ) {
+        let center = (center.x as f32, center.y as f32);
+        let half_width = width / 2.0;
+        let segments = line::arc_segments(
+            (radius_x.max(radius_y) + half_width) * self.get_transform().max_scale(),
+            2.0 * PI,
+        );
+
+        let outer = shapes::ellipse_points(
+            center,
+            radius_x + half_width,
+            radius_y + half_width,
+            0.0,
+            2.0 * PI,
+            segments,
+        );
+        let inner = shapes::ellipse_points(
+            center,
+            (radius_x - half_width).max(0.0),
+            (radius_y - half_width).max(0.0),
+            0.0,
+            2.0 * PI,
+            segments,
+        );
+        let triangles = shapes::fill_band(&outer, &inner);
+
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws part of the outline of a circle, from one angle to another, with the specified
+    /// radius and line width in pixels. Angles are in radians, clockwise from the right
+    /// hand side of the circle. Arcs of more than a full turn are drawn as a full circle,
+    /// and nothing is drawn if any of the values aren't finite.
+    fn draw_arc(
+        &mut self,
+        center: &Position,
+        radius: f32,
+        start: f32,
+        end: f32,
+        width: f32,
+        color: &Color,
+    ) {
+        if [radius, start, end, width]
+            .iter()
+            .any(|value| !value.is_finite())
+        {
+            return;
+        }
+
+        let center = (center.x as f32, center.y as f32);
+        let sweep = line::clamp_sweep(end - start);
+        // Large angles leave too little precision to tell the two ends apart
+        let start = start % (2.0 * PI);
+        let end = start + sweep;
+        let half_width = width / 2.0;
+        let segments = line::arc_segments(
+            (radius + half_width) * self.get_transform().max_scale(),
+            end - start,
+        );
+
+        let outer = shapes::ellipse_points(
+            center,
+            radius + half_width,
+            radius + half_width,
+            start,
+            end,
+            segments,
+        );
+        let inner_radius = (radius - half_width).max(0.0);
+        let inner =
+            shapes::ellipse_points(center, inner_radius, inner_radius, start, end, segments);
+        let triangles = shapes::fill_band(&outer, &inner);
+
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws a filled slice of a circle, from one angle to another, with the specified
+    /// radius in pixels. Angles are in radians, clockwise from the right hand side of the
+    /// circle. Slices of more than a full turn are drawn as a full circle, and nothing is
+    /// drawn if any of the values aren't finite.
+    fn draw_pie(&mut self, center: &Position, radius: f32, start: f32, end: f32, color: &Color) {
+        if [radius, start, end].iter().any(|value| !value.is_finite()) {
+            return;
+        }
+
+        let center = (center.x as f32, center.y as f32);
+        let sweep = line::clamp_sweep(end - start);
+        // Large angles leave too little precision to tell the two ends apart
+        let start = start % (2.0 * PI);
+        let end = start + sweep;
+        let segments = line::arc_segments(radius * self.get_transform().max_scale(), end - start);
+
+        let outline = shapes::ellipse_points(center, radius, radius, start, end, segments);
+        let triangles = shapes::fill_fan(center, &outline);
+
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws a filled rectangle with rounded corners, with the specified corner radius in
+    /// pixels.
+    fn draw_rounded_rect(&mut self, rect: &Rect, radius: f32, color: &Color) {
+        let triangles = shapes::fill_rounded_rect(rect, radius, self.get_transform().max_scale());
+
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws the outline of a rectangle with rounded corners, with the specified corner
+    /// radius and line width in pixels. The line is centered on the edge of the rectangle.
+    fn draw_rounded_rect_outline(&mut self, rect: &Rect, radius: f32, width: f32, color: &Color) {
+        let half_width = width / 2.0;
+        let segments = line::arc_segments(
+            (radius + half_width) * self.get_transform().max_scale(),
+            PI / 2.0,
+        );
+
+        // Square corners stay square, rather than being rounded off by the line width
+        let outer_radius = if radius > 0.0 {
+            radius + half_width
+        } else {
+            0.0
+        };
+        let outer = shapes::rounded_rect_points(
+            rect.x as f32 - half_width,
+            rect.y as f32 - half_width,
+            rect.width as f32 + width,
+            rect.height as f32 + width,
+            outer_radius,
+            segments,
+        );
+
+        // Shrink towards the center, without turning inside out
+        let inset_x = half_width.min(rect.width as f32 / 2.0);
+        let inset_y = half_width.min(rect.height as f32 / 2.0);
+        let inner = shapes::rounded_rect_points(
+            rect.x as f32 + inset_x,
+            rect.y as f32 + inset_y,
+            rect.width as f32 - inset_x * 2.0,
+            rect.height as f32 - inset_y * 2.0,
+            radius - half_width,
+            segments,
+        );
+        let triangles = shapes::fill_band(&outer, &inner);
+
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Fills the inside of a path, using the specified rule to decide which parts of the
+    /// path are inside when it overlaps itself. Open subpaths are closed with a straight
+    /// line.
+    fn fill_path(&mut self, path: &Path, rule: FillRule, color: &Color) {
+        let subpaths: Vec<Vec<(f32, f32)>> = path
+            .flatten(self.get_transform().max_scale())
+            .into_iter()
+            .map(|(points, _)| points)
+            .collect();
+
+        let triangles = path::tessellate_fill(&subpaths, rule);
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws along the lines and curves of a path, with the specified width in pixels, and
+    /// the specified joins between segments and caps on the ends of open subpaths.
+    fn stroke_path(
+        &mut self,
+        path: &Path,
+        width: f32,
+        color: &Color,
+        join: LineJoin,
+        cap: LineCap,
+    ) {
+        let mut triangles = Vec::new();
+        for (points, closed) in path.flatten(self.get_transform().max_scale()) {
+            triangles.extend(line::tessellate_polyline(&points, closed, width, join, cap));
+        }
+
+        let colors = repeat_color(color, triangles.len() / 2);
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws a list of triangles, filled with a gradient. Points are in pixels, as x/y pairs
+    /// with 3 points per triangle, and have the current transform applied, along with the
+    /// gradient.
+    fn draw_gradient_triangles(&mut self, points: &[f32], gradient: &Gradient) {
+        let (triangles, colors) =
+            gradient.paint_triangles(points, self.get_transform().max_scale());
+        self.draw_colored_triangles(&triangles, &colors)
+    }
+
+    /// Draws a rectangle filled with a gradient.
+    fn draw_gradient_rect(&mut self, rect: &Rect, gradient: &Gradient) {
+        self.draw_gradient_triangles(&shapes::fill_rect(rect), gradient)
+    }
+
+    /// Draws an ellipse filled with a gradient, with the specified horizontal and vertical
+    /// radii in pixels.
+    fn draw_gradient_ellipse(
+        &mut self,
+        center: &Position,
+        radius_x: f32,
+        radius_y: f32,
+        gradient: &Gradient,
+    ) {
+        let triangles = shapes::fill_ellipse(
+            (center.x as f32, center.y as f32),
+            radius_x,
+            radius_y,
+            self.get_transform().max_scale(),
+        );
+        self.draw_gradient_triangles(&triangles, gradient)
+    }
+
+    /// Draws a rectangle with rounded corners filled with a gradient, with the specified
+    /// corner radius in pixels.
+    fn draw_gradient_rounded_rect(&mut self, rect: &Rect, radius: f32, gradient: &Gradient) {
+        let triangles = shapes::fill_rounded_rect(rect, radius, self.get_transform().max_scale());
+        self.draw_gradient_triangles(&triangles, gradient)
+    }
+
+    /// Fills the inside of a path with a gradient. See `fill_path`.
+    fn fill_path_gradient(&mut self, path: &Path, rule: FillRule, gradient: &Gradient) {
+        let subpaths: Vec<Vec<(f32, f32)>> = path
+            .flatten(self.get_transform().max_scale())
+            .into_iter()
+            .map(|(points, _)| points)
+            .collect();
+
+        self.draw_gradient_triangles(&path::tessellate_fill(&subpaths, rule), gradient)
+    }
+
+    /// Converts a point in pixels to OpenGL coordinates, applying the current transform.
+    fn pixel_to_vertex(&self, x: f32, y: f32) -> [f32; 2] {
+        let (x, y) = self.get_transform().apply(x, y);
+
+        // Inverted due to OpenGL perspective
+        [
+            x / self.get_width() as f32 * 2.0 - 1.0,
+            -(y / self.get_height() as f32 * 2.0 - 1.0),
+        ]
+    }
+
+    /// Converts a rectangle to 4 vertices, applying the current transform.
     fn rect_to_vertices(&self, rect: &Rect) -> [f32; 12] {
         // Translate to OpenGL coordinates
-        let min_x = (rect.x as f32) / self.get_width() as f32 * 2.0 - 1.0;
-        let max_x = ((rect.x + rect.width) as f32) / self.get_width() as f32 * 2.0 - 1.0;
-        let min_y = (rect.y as f32) / self.get_height() as f32 * 2.0 - 1.0;
-        let max_y = ((rect.y + rect.height) as f32) / self.get_height() as f32 * 2.0 - 1.0;
+        let to_gl = |x: i32, y: i32| {
+            let [x, y] = self.pixel_to_vertex(x as f32, y as f32);
+            (x, y)
+        };
+
+        let (x1, y1) = to_gl(rect.x, rect.y);
+        let (x2, y2) = to_gl(rect.x, rect.y + rect.height);
+        let (x3, y3) = to_gl(rect.x + rect.width, rect.y + rect.height);
+        let (x4, y4) = to_gl(rect.x + rect.width, rect.y);
 
         // Generate vertex data
-        // Inverted due to OpenGL perspective
         [
             // Vertex 1
-            min_x, -min_y, min_x, -max_y, max_x, -max_y, // Vertex 2
-            min_x, -min_y, max_x, -min_y, max_x, -max_y,
+            x1, y1, x2, y2, x3, y3, // Vertex 2
+            x1, y1, x4, y4, x3, y3,
         ]
     }
 
     /// Creates a new instance of this drawer.
     ///
     /// All parameters are handled only if the target platform handles them.
-    fn new(title: &str, width: u32, height: u32) -> Result<Self, String>
+    fn new(title: &str, width: u32, height: u32) -> Result<Self, Error>
+    where
+        Self: std::marker::Sized,
+    {
+        Self::with_config(DrawerConfig::new(title, width, height))
+    }
+
+    /// Creates a new instance of this drawer, with the window set up as described by the
+    /// config. Options which this platform can't honour are skipped, and listed by
+    /// `config_warnings`.
+    fn with_config(config: DrawerConfig) -> Result<Self, Error>
+    where
+        Self: std::marker::Sized;
+
+    /// Returns a description of each option in the config this drawer was created with
+    /// which couldn't be honoured.
+    fn config_warnings(&self) -> &[String] {
+        &[]
+    }
+
+    /// Creates a new instance of this drawer which doesn't open a window, instead drawing
+    /// into an offscreen framebuffer of the specified size. Use `capture()` to retrieve
+    /// the result.
+    fn new_headless(width: u32, height: u32) -> Result<Self, Error>
     where
         Self: std::marker::Sized;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::render::software::drawer::SoftwareDrawer;
+
+    /// Counts the pixels in a frame which aren't black.
+    fn count_drawn(drawer: &mut SoftwareDrawer) -> usize {
+        drawer
+            .capture()
+            .pixels()
+            .filter(|pixel| pixel[0] != 0 || pixel[1] != 0 || pixel[2] != 0)
+            .count()
+    }
+
+    /// Draws a frame with a new 64x64 drawer, returning how many pixels were drawn.
+    fn draw<F: FnOnce(&mut SoftwareDrawer)>(draw: F) -> usize {
+        let mut drawer = SoftwareDrawer::new_headless(64, 64).unwrap();
+        drawer.start();
+        drawer.clear(false);
+        draw(&mut drawer);
+        count_drawn(&mut drawer)
+    }
+
+    #[test]
+    fn arcs_beyond_a_full_turn_draw_a_circle() {
+        let center = Position::new(32, 32);
+        let white = Color::new_3byte(255, 255, 255);
+
+        let circle = draw(|drawer| drawer.draw_pie(&center, 20.0, 0.0, 2.0 * PI, &white));
+        assert!(circle > 0);
+        assert_eq!(
+            draw(|drawer| drawer.draw_pie(&center, 20.0, 0.0, 1e9, &white)),
+            circle
+        );
+        // Starting somewhere else on the circle only moves the edges between triangles
+        let shifted = draw(|drawer| drawer.draw_pie(&center, 20.0, 1e9, -1e9, &white));
+        assert!((shifted as f32 / circle as f32 - 1.0).abs() < 0.01);
+
+        let outline = draw(|drawer| drawer.draw_arc(&center, 20.0, 0.0, 2.0 * PI, 4.0, &white));
+        assert!(outline > 0);
+        assert_eq!(
+            draw(|drawer| drawer.draw_arc(&center, 20.0, 0.0, 1e9, 4.0, &white)),
+            outline
+        );
+    }
+
+    #[test]
+    fn arcs_which_arent_finite_draw_nothing() {
+        let center = Position::new(32, 32);
+        let white = Color::new_3byte(255, 255, 255);
+
+        for &value in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(
+                draw(|drawer| drawer.draw_arc(&center, 20.0, 0.0, value, 4.0, &white)),
+                0
+            );
+            assert_eq!(
+                draw(|drawer| drawer.draw_arc(&center, 20.0, value, 1.0, 4.0, &white)),
+                0
+            );
+            assert_eq!(
+                draw(|drawer| drawer.draw_arc(&center, value, 0.0, 1.0, 4.0, &white)),
+                0
+            );
+            assert_eq!(
+                draw(|drawer| drawer.draw_arc(&center, 20.0, 0.0, 1.0, value, &white)),
+                0
+            );
+            assert_eq!(
+                draw(|drawer| drawer.draw_pie(&center, 20.0, 0.0, value, &white)),
+                0
+            );
+            assert_eq!(
+                draw(|drawer| drawer.draw_pie(&center, value, 0.0, 1.0, &white)),
+                0
+            );
+        }
+    }
+}
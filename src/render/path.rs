@@ -0,0 +1,570 @@
+//! Vector paths built from lines and curves, which can be filled or stroked.
+
+use crate::render::line::arc_segments;
+use crate::render::line::push_triangle;
+
+use std::f32::consts::PI;
+
+/// How the inside of a path is decided, when it overlaps itself.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum FillRule {
+    /// A point is inside if the path winds around it more times in one direction than the
+    /// other.
+    NonZero,
+    /// A point is inside if a line from it crosses the path an odd number of times. Holes
+    /// are left wherever the path overlaps itself.
+    EvenOdd,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    QuadTo(f32, f32, f32, f32),
+    CubicTo(f32, f32, f32, f32, f32, f32),
+    Arc {
+        center: (f32, f32),
+        radius: f32,
+        start: f32,
+        end: f32,
+    },
+    Close,
+}
+
+/// A shape made up of lines and curves, in pixels. A path can contain several separate
+/// outlines (subpaths), each started with `move_to`.
+///
+/// ```rust
+/// use leafrender::render::path::Path;
+///
+/// let arrow = Path::new()
+///     .move_to(0.0, 10.0)
+///     .line_to(20.0, 10.0)
+///     .quad_to(30.0, 10.0, 30.0, 0.0)
+///     .close();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    segments: Vec<Segment>,
+    start: Option<(f32, f32)>,
+    current: Option<(f32, f32)>,
+}
+
+impl Path {
+    /// Starts a new subpath at the specified point.
+    pub fn move_to(mut self, x: f32, y: f32) -> Self {
+        self.segments.push(Segment::MoveTo(x, y));
+        self.start = Some((x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a straight line from the current point. Starts a new subpath if there is no
+    /// current point.
+    pub fn line_to(mut self, x: f32, y: f32) -> Self {
+        if self.current.is_none() {
+            return self.move_to(x, y);
+        }
+
+        self.segments.push(Segment::LineTo(x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a quadratic Bézier curve from the current point, bending towards the control
+    /// point.
+    pub fn quad_to(mut self, control_x: f32, control_y: f32, x: f32, y: f32) -> Self {
+        if self.current.is_none() {
+            self = self.move_to(control_x, control_y);
+        }
+
+        self.segments
+            .push(Segment::QuadTo(control_x, control_y, x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a cubic Bézier curve from the current point, bending towards both control
+    /// points.
+    pub fn cubic_to(
+        mut self,
+        control1_x: f32,
+        control1_y: f32,
+        control2_x: f32,
+        control2_y: f32,
+        x: f32,
+        y: f32,
+    ) -> Self {
+        if self.current.is_none() {
+            self = self.move_to(control1_x, control1_y);
+        }
+
+        self.segments.push(Segment::CubicTo(
+            control1_x, control1_y, control2_x, control2_y, x, y,
+        ));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a circular arc with the specified radius, which rounds off the corner between
+    /// a line from the current point to the first point, and a line from the first point to
+    /// the second. The arc is joined to the current point with a straight line, and the
+    /// path ends where the arc touches the second line.
+    ///
+    /// This works the same as `arcTo` in the HTML canvas.
+    pub fn arc_to(self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) -> Self {
+        let current = match self.current {
+            Some(current) => current,
+            None => return self.move_to(x1, y1),
+        };
+
+        let to_current = (current.0 - x1, current.1 - y1);
+        let to_next = (x2 - x1, y2 - y1);
+        let length_current = (to_current.0 * to_current.0 + to_current.1 * to_current.1).sqrt();
+        let length_next = (to_next.0 * to_next.0 + to_next.1 * to_next.1).sqrt();
+
+        if radius <= 0.0 || length_current == 0.0 || length_next == 0.0 {
+            return self.line_to(x1, y1);
+        }
+
+        let to_current = (to_current.0 / length_current, to_current.1 / length_current);
+        let to_next = (to_next.0 / length_next, to_next.1 / length_next);
+
+        // Lines which are (almost) straight have no corner to round off
+        let cos_angle = to_current.0 * to_next.0 + to_current.1 * to_next.1;
+        if cos_angle.abs() > 0.9999 {
+            return self.line_to(x1, y1);
+        }
+
+        let half_angle = cos_angle.acos() / 2.0;
+        let tangent_distance = radius / half_angle.tan();
+        let center_distance = radius / half_angle.sin();
+
+        let bisector = (to_current.0 + to_next.0, to_current.1 + to_next.1);
+        let bisector_length = (bisector.0 * bisector.0 + bisector.1 * bisector.1).sqrt();
+
+        let center = (
+            x1 + bisector.0 / bisector_length * center_distance,
+            y1 + bisector.1 / bisector_length * center_distance,
+        );
+        let from = (
+            x1 + to_current.0 * tangent_distance,
+            y1 + to_current.1 * tangent_distance,
+        );
+        let to = (
+            x1 + to_next.0 * tangent_distance,
+            y1 + to_next.1 * tangent_distance,
+        );
+
+        let start = (from.1 - center.1).atan2(from.0 - center.0);
+        let mut end = (to.1 - center.1).atan2(to.0 - center.0);
+
+        // Take the short way around
+        if end - start > PI {
+            end -= 2.0 * PI;
+        } else if end - start < -PI {
+            end += 2.0 * PI;
+        }
+
+        let mut path = self.line_to(from.0, from.1);
+        path.segments.push(Segment::Arc {
+            center,
+            radius,
+            start,
+            end,
+        });
+        path.current = Some(to);
+        path
+    }
+
+    /// Closes the current subpath, with a straight line back to where it started.
+    pub fn close(mut self) -> Self {
+        if self.current.is_some() {
+            self.segments.push(Segment::Close);
+            self.current = self.start;
+        }
+
+        self
+    }
+
+    /// Checks if this path has nothing in it.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Converts this path into a set of polylines, and whether each one is closed. Curves
+    /// are split finely enough to look smooth once scaled by the specified amount.
+    pub(crate) fn flatten(&self, scale: f32) -> Vec<(Vec<(f32, f32)>, bool)> {
+        let mut subpaths = Vec::new();
+        let mut points: Vec<(f32, f32)> = Vec::new();
+
+        for segment in &self.segments {
+            let current = points.last().cloned().unwrap_or((0.0, 0.0));
+
+            match *segment {
+                Segment::MoveTo(x, y) => {
+                    if points.len() > 1 {
+                        subpaths.push((points, false));
+                    }
+                    points = vec![(x, y)];
+                }
+                Segment::LineTo(x, y) => points.push((x, y)),
+                Segment::QuadTo(control_x, control_y, x, y) => {
+                    let control = (control_x, control_y);
+                    let end = (x, y);
+
+                    let steps = curve_steps(0.25, &[current, control, end], scale);
+                    for i in 1..=steps {
+                        let t = i as f32 / steps as f32;
+                        let u = 1.0 - t;
+                        points.push((
+                            u * u * current.0 + 2.0 * u * t * control.0 + t * t * end.0,
+                            u * u * current.1 + 2.0 * u * t * control.1 + t * t * end.1,
+                        ));
+                    }
+                }
+                Segment::CubicTo(control1_x, control1_y, control2_x, control2_y, x, y) => {
+                    let control1 = (control1_x, control1_y);
+                    let control2 = (control2_x, control2_y);
+                    let end = (x, y);
+
+                    let steps = curve_steps(0.75, &[current, control1, control2, end], scale);
+                    for i in 1..=steps {
+                        let t = i as f32 / steps as f32;
+                        let u = 1.0 - t;
+                        let a = u * u * u;
+                        let b = 3.0 * u * u * t;
+                        let c = 3.0 * u * t * t;
+                        let d = t * t * t;
+                        points.push((
+                            a * current.0 + b * control1.0 + c * control2.0 + d * end.0,
+                            a * current.1 + b * control1.1 + c * control2.1 + d * end.1,
+                        ));
+                    }
+                }
+                Segment::Arc {
+                    center,
+                    radius,
+                    start,
+                    end,
+                } => {
+                    let steps = arc_segments(radius * scale, end - start);
+                    for i in 0..=steps {
+                        let angle = start + (end - start) * i as f32 / steps as f32;
+                        points.push((
+                            center.0 + angle.cos() * radius,
+                            center.1 + angle.sin() * radius,
+                        ));
+                    }
+                }
+                Segment::Close => {
+                    if let Some(&start) = points.first() {
+                        if points.len() > 1 {
+                            subpaths.push((points, true));
+                        }
+                        points = vec![start];
+                    }
+                }
+            }
+        }
+
+        if points.len() > 1 {
+            subpaths.push((points, false));
+        }
+
+        subpaths
+    }
+
+    /// Creates a new, empty path.
+    pub fn new() -> Self {
+        Path {
+            segments: Vec::new(),
+            start: None,
+            current: None,
+        }
+    }
+}
+
+impl Default for Path {
+    fn default() -> Self {
+        Path::new()
+    }
+}
+
+/// Returns how many straight lines a Bézier curve should be split into, to stay within a
+/// quarter of a pixel of the real curve. `factor` depends on the degree of the curve.
+fn curve_steps(factor: f32, points: &[(f32, f32)], scale: f32) -> usize {
+    let mut max_change: f32 = 0.0;
+
+    for window in points.windows(3) {
+        let x = window[0].0 - 2.0 * window[1].0 + window[2].0;
+        let y = window[0].1 - 2.0 * window[1].1 + window[2].1;
+        max_change = max_change.max((x * x + y * y).sqrt());
+    }
+
+    let steps = (factor * max_change * scale / 0.25).sqrt().ceil();
+    (steps as usize).clamp(1, 128)
+}
+
+/// One edge of a filled shape, pointing down the screen.
+struct Edge {
+    top: (f32, f32),
+    bottom: (f32, f32),
+    winding: i32,
+}
+
+impl Edge {
+    fn x_at(&self, y: f32) -> f32 {
+        let t = (y - self.top.1) / (self.bottom.1 - self.top.1);
+        self.top.0 + (self.bottom.0 - self.top.0) * t
+    }
+}
+
+/// Tessellates the inside of a set of polylines into a list of triangles (x/y pairs, 3
+/// points per triangle). Every polyline is treated as closed.
+///
+/// The shape is cut into horizontal bands at every point and crossing, and each band is
+/// filled with trapezoids between the edges passing through it, so any shape is allowed.
+/// Points which aren't finite are skipped.
+pub(crate) fn tessellate_fill(subpaths: &[Vec<(f32, f32)>], rule: FillRule) -> Vec<f32> {
+    let mut triangles = Vec::new();
+    let mut edges = Vec::new();
+    let mut heights = Vec::new();
+
+    for points in subpaths {
+        let points: Vec<(f32, f32)> = points
+            .iter()
+            .cloned()
+            .filter(|point| point.0.is_finite() && point.1.is_finite())
+            .collect();
+
+        for (i, &from) in points.iter().enumerate() {
+            let to = points[(i + 1) % points.len()];
+            heights.push(from.1);
+
+            // Horizontal edges don't cover any bands
+            if from.1 < to.1 {
+                edges.push(Edge {
+                    top: from,
+                    bottom: to,
+                    winding: 1,
+                });
+            } else if from.1 > to.1 {
+                edges.push(Edge {
+                    top: to,
+                    bottom: from,
+                    winding: -1,
+                });
+            }
+        }
+    }
+
+    heights.sort_by(f32::total_cmp);
+    heights.dedup();
+
+    for band in heights.windows(2) {
+        let mut pending = vec![(band[0], band[1])];
+
+        while let Some((top, bottom)) = pending.pop() {
+            let mut active: Vec<(f32, f32, i32)> = edges
+                .iter()
+                .filter(|edge| edge.top.1 <= top && edge.bottom.1 >= bottom)
+                .map(|edge| (edge.x_at(top), edge.x_at(bottom), edge.winding))
+                .collect();
+
+            // Edges which cross inside this band need it split at the first crossing.
+            // Crossings right at the edge of the band (such as where it was last split)
+            // don't change the order of the edges, so are ignored.
+            let mut crossing = bottom;
+            for (i, a) in active.iter().enumerate() {
+                for b in &active[i + 1..] {
+                    let top_distance = a.0 - b.0;
+                    let bottom_distance = a.1 - b.1;
+
+                    if top_distance * bottom_distance < 0.0 {
+                        let t = top_distance / (top_distance - bottom_distance);
+                        let y = top + (bottom - top) * t;
+                        if y - top > 1e-3 && bottom - y > 1e-3 {
+                            crossing = crossing.min(y);
+                        }
+                    }
+                }
+            }
+
+            if crossing < bottom {
+                pending.push((crossing, bottom));
+                pending.push((top, crossing));
+                continue;
+            }
+
+            active.sort_by(|a, b| (a.0 + a.1).total_cmp(&(b.0 + b.1)));
+
+            let mut winding = 0;
+            for (i, edge) in active.iter().enumerate() {
+                winding += edge.2;
+
+                let inside = match rule {
+                    FillRule::NonZero => winding != 0,
+                    FillRule::EvenOdd => winding % 2 != 0,
+                };
+
+                if inside && i + 1 < active.len() {
+                    let next = &active[i + 1];
+                    push_triangle(
+                        &mut triangles,
+                        (edge.0, top),
+                        (edge.1, bottom),
+                        (next.1, bottom),
+                    );
+                    push_triangle(
+                        &mut triangles,
+                        (edge.0, top),
+                        (next.1, bottom),
+                        (next.0, top),
+                    );
+                }
+            }
+        }
+    }
+
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sums the area of a list of triangles.
+    fn area(triangles: &[f32]) -> f32 {
+        triangles
+            .chunks(6)
+            .map(|t| ((t[2] - t[0]) * (t[5] - t[1]) - (t[4] - t[0]) * (t[3] - t[1])).abs() / 2.0)
+            .sum()
+    }
+
+    /// Checks if a point lies inside any of a list of triangles.
+    fn covers(triangles: &[f32], x: f32, y: f32) -> bool {
+        triangles.chunks(6).any(|t| {
+            let side =
+                |ax: f32, ay: f32, bx: f32, by: f32| (bx - ax) * (y - ay) - (by - ay) * (x - ax);
+            let a = side(t[0], t[1], t[2], t[3]);
+            let b = side(t[2], t[3], t[4], t[5]);
+            let c = side(t[4], t[5], t[0], t[1]);
+            (a >= 0.0 && b >= 0.0 && c >= 0.0) || (a <= 0.0 && b <= 0.0 && c <= 0.0)
+        })
+    }
+
+    /// A five-pointed star, drawn as a single self-intersecting outline.
+    fn star() -> Vec<(f32, f32)> {
+        (0..5)
+            .map(|i| {
+                let angle = (i as f32 * 144.0 - 90.0).to_radians();
+                (16.0 + angle.cos() * 14.0, 16.0 + angle.sin() * 14.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fills_convex_shape() {
+        let square = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+
+        for &rule in &[FillRule::NonZero, FillRule::EvenOdd] {
+            let triangles = tessellate_fill(std::slice::from_ref(&square), rule);
+            assert!((area(&triangles) - 100.0).abs() < 1e-3);
+            assert!(covers(&triangles, 5.0, 5.0));
+            assert!(!covers(&triangles, 15.0, 5.0));
+        }
+    }
+
+    #[test]
+    fn fills_concave_shape() {
+        // An L shape, with the top right quarter missing
+        let shape = vec![
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (20.0, 10.0),
+            (20.0, 20.0),
+            (0.0, 20.0),
+        ];
+
+        let triangles = tessellate_fill(&[shape], FillRule::NonZero);
+        assert!((area(&triangles) - 300.0).abs() < 1e-3);
+        assert!(covers(&triangles, 5.0, 5.0));
+        assert!(covers(&triangles, 15.0, 15.0));
+        assert!(!covers(&triangles, 15.0, 5.0));
+    }
+
+    #[test]
+    fn fills_self_intersecting_shape() {
+        let non_zero = tessellate_fill(&[star()], FillRule::NonZero);
+        let even_odd = tessellate_fill(&[star()], FillRule::EvenOdd);
+
+        // The middle of the star is wound around twice
+        assert!(covers(&non_zero, 16.0, 16.0));
+        assert!(!covers(&even_odd, 16.0, 16.0));
+
+        // All of the pentagon in the middle is a hole, including below the crossings on
+        // either side of it
+        assert!(!covers(&even_odd, 16.0, 19.0));
+        assert!(covers(&non_zero, 16.0, 19.0));
+
+        // The points of the star are filled either way
+        assert!(covers(&non_zero, 16.0, 5.0));
+        assert!(covers(&even_odd, 16.0, 5.0));
+
+        assert!(area(&even_odd) < area(&non_zero));
+    }
+
+    #[test]
+    fn fills_overlapping_subpaths() {
+        let a = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let b = vec![(5.0, 5.0), (15.0, 5.0), (15.0, 15.0), (5.0, 15.0)];
+
+        let non_zero = tessellate_fill(&[a.clone(), b.clone()], FillRule::NonZero);
+        assert!((area(&non_zero) - 175.0).abs() < 1e-3);
+
+        let even_odd = tessellate_fill(&[a, b], FillRule::EvenOdd);
+        assert!((area(&even_odd) - 150.0).abs() < 1e-3);
+        assert!(!covers(&even_odd, 7.5, 7.5));
+    }
+
+    #[test]
+    fn skips_degenerate_shapes() {
+        let shapes = vec![
+            vec![],
+            vec![(5.0, 5.0)],
+            vec![(0.0, 0.0), (10.0, 10.0)],
+            vec![(0.0, 0.0), (5.0, 5.0), (10.0, 10.0)],
+            vec![(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)],
+        ];
+
+        for shape in shapes {
+            assert!(area(&tessellate_fill(&[shape], FillRule::NonZero)) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn skips_points_which_are_not_finite() {
+        let shape = vec![
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (f32::NAN, 5.0),
+            (10.0, 10.0),
+            (0.0, f32::INFINITY),
+            (0.0, 10.0),
+        ];
+
+        let triangles = tessellate_fill(&[shape], FillRule::NonZero);
+        assert!((area(&triangles) - 100.0).abs() < 1e-3);
+
+        let path = Path::new()
+            .move_to(f32::NAN, f32::NAN)
+            .line_to(10.0, f32::NEG_INFINITY)
+            .quad_to(f32::NAN, 0.0, 5.0, 5.0)
+            .close();
+        for (points, _) in path.flatten(1.0) {
+            tessellate_fill(&[points], FillRule::EvenOdd);
+        }
+    }
+}
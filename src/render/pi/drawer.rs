@@ -2,12 +2,12 @@
 
 use opengles::glesv2 as gl;
 
+use image::imageops;
 use image::DynamicImage;
 use image::RgbaImage;
 
 use videocore::dispmanx;
 use videocore::dispmanx::ResourceHandle;
-use videocore::dispmanx::Transform;
 use videocore::image::ImageType;
 use videocore::image::Rect as VCRect;
 
@@ -15,16 +15,39 @@ use libc::c_void;
 
 use videocore::bcm_host::GraphicsDisplaySize;
 
+use crate::render::batch::Batch;
+use crate::render::clip::ClipStack;
+use crate::render::config::DrawerConfig;
 use crate::render::pi::gl_context::Context;
+use crate::render::transform::Transform;
+use crate::render::transform::TransformStack;
 
 use crate::render::pi::brightness::set_brightness;
+use crate::render::pi::framebuffer::GLFramebuffer;
 
 use crate::render::pi::shader::GLSLShader;
 use crate::render::pi::texture::GlTexture;
+use crate::render::pi::texture::ReleaseQueue;
 use crate::render::pi::vbo::GLVBO;
 
+use crate::render::check_texture_update;
+use crate::render::target::RenderTarget;
+use crate::render::BlendMode;
+use crate::render::Dimensions;
 use crate::render::Drawer;
+use crate::render::PixelFormat;
 use crate::render::Texture;
+use crate::render::TextureOptions;
+
+use crate::pos::Rect;
+use crate::Error;
+
+/// A render target which draws are currently redirected into.
+struct BoundTarget {
+    framebuffer: gl::GLuint,
+    width: usize,
+    height: usize,
+}
 
 #[derive(Ord, PartialOrd, Eq, PartialEq)]
 enum DrawState {
@@ -55,18 +78,101 @@ pub struct PiDrawer {
     uv: GLVBO,
     attr_textured_uv: gl::GLint,
 
+    // Set to whether draws are premultiplied, and whether the bound texture is
+    uniform_colored_premultiply: gl::GLint,
+    uniform_textured_premultiply: gl::GLint,
+    uniform_textured_premultiplied_tex: gl::GLint,
+    uniform_textured_alpha_tex: gl::GLint,
+
     context: Context,
 
     bg: Option<ResourceHandle>,
 
+    // Draws waiting to be submitted, and the texture they use
+    batch: Batch,
+    batch_texture: Option<gl::GLuint>,
+    batch_premultiplied: bool,
+    // Textures dropped while the batch may still have been using them
+    releases: ReleaseQueue,
+    batch_alpha: bool,
+
+    premultiplied_alpha: bool,
+
+    transforms: TransformStack,
+    clips: ClipStack,
+    blend_mode: BlendMode,
+    target: Option<BoundTarget>,
+    // Why the screen couldn't be resized at the start of this frame, reported by `end()`
+    resize_error: Option<Error>,
+
     // Debugging information
     transitions: usize,
+
+    config_warnings: Vec<String>,
 }
 
 impl PiDrawer {
+    /// Returns the size of the screen as of the start of this frame. Unlike `get_width` and
+    /// `get_height`, this ignores any render target which is bound.
+    pub(crate) fn get_screen_size(&self) -> (usize, usize) {
+        (self.size.width as usize, self.size.height as usize)
+    }
+
+    /// Returns the size the display has right now, which the screen is resized to at the
+    /// start of the next frame. Headless drawers keep the size they were created with.
+    pub(crate) fn get_display_size(&self) -> (usize, usize) {
+        if self.context.layers.is_none() {
+            return self.get_screen_size();
+        }
+
+        match Context::get_resolution() {
+            Ok(size) => (size.width as usize, size.height as usize),
+            Err(_) => self.get_screen_size(),
+        }
+    }
+
+    /// Updates the scissor test to match the current clip.
+    fn apply_clip(&mut self) {
+        // Anything batched was drawn under the previous clip
+        self.flush();
+
+        match self.clips.get() {
+            Some(rect) => {
+                // OpenGL's origin is at the bottom left, but render targets are flipped
+                let y = if self.target.is_some() {
+                    rect.y
+                } else {
+                    self.get_height() as i32 - (rect.y + rect.height)
+                };
+
+                gl::enable(gl::GL_SCISSOR_TEST);
+                gl::scissor(rect.x, y, rect.width, rect.height);
+            }
+            None => gl::disable(gl::GL_SCISSOR_TEST),
+        }
+    }
+
+    /// Binds the framebuffer which draws should go into, and sizes the viewport to match.
+    fn bind_output(&mut self) {
+        // Anything batched was drawn into the previous output
+        self.flush();
+
+        match self.target {
+            Some(ref target) => gl::bind_framebuffer(gl::GL_FRAMEBUFFER, target.framebuffer),
+            None => GLFramebuffer::unbind(),
+        }
+
+        self.batch.flip_y = self.target.is_some();
+
+        gl::viewport(0, 0, self.get_width() as i32, self.get_height() as i32);
+
+        self.apply_clip();
+    }
+
     /// Changes shaders, and ensures that GLES is ready to use it.
     fn configure_state(&mut self, target: DrawState) {
         if self.state != target {
+            self.flush();
             self.transitions += 1;
 
             // Unbind previous state, if needed
@@ -159,6 +265,18 @@ impl PiDrawer {
             self.state = target;
         }
     }
+
+    /// Deletes textures which were dropped while the batch may still have been using them.
+    fn delete_released_textures(&mut self) {
+        let released = match self.releases.lock() {
+            Ok(mut releases) => std::mem::take(&mut *releases),
+            Err(_) => return,
+        };
+
+        if !released.is_empty() {
+            gl::delete_textures(&released);
+        }
+    }
 }
 
 impl Drawer for PiDrawer {
@@ -166,23 +284,55 @@ impl Drawer for PiDrawer {
 
     fn start(&mut self) {
         self.transitions = 0;
-        self.size = Context::get_resolution();
+        if self.context.layers.is_some() {
+            // Keep the last known size if the display can't be queried, or the surface
+            // couldn't be moved over to the new one. Failed resizes are tried again next
+            // frame.
+            if let Ok(size) = Context::get_resolution() {
+                if size.width != self.size.width || size.height != self.size.height {
+                    match self.context.resize(&size) {
+                        Ok(()) => self.size = size,
+                        Err(err) => self.resize_error = Some(err),
+                    }
+                }
+            }
+        }
         self.state = DrawState::None;
+        self.transforms.clear();
+        self.clips.clear();
+
+        self.target = None;
+        self.bind_output();
+
+        self.set_blend_mode(BlendMode::None);
     }
 
     /// Ends this frame.
-    fn end(&mut self) {
+    fn end(&mut self) -> Result<(), Error> {
+        self.set_render_target(None);
+        self.set_blend_mode(BlendMode::None);
         self.configure_state(DrawState::None);
+        self.batch_texture = None;
 
-        gl::disable(gl::GL_BLEND);
+        // The surface may not exist if resizing failed, so there is nothing to present
+        if let Some(err) = self.resize_error.take() {
+            return Err(err);
+        }
 
         if !self.context.swap_buffers() {
-            panic!("Failed to swap buffers!");
+            return Err(Error::Context("Failed to swap buffers".into()));
         }
+
+        Ok(())
     }
 
     /// Clears the framebuffer.
     fn clear(&mut self, transparent: bool) {
+        self.flush();
+
+        // The background is a separate dispmanx layer underneath the screen, so shows
+        // through wherever the screen is transparent. Render targets are never composited
+        // with it.
         if transparent {
             gl::clear_color(0.0, 0.0, 0.0, 0.0);
         } else {
@@ -191,33 +341,118 @@ impl Drawer for PiDrawer {
         gl::clear(gl::GL_COLOR_BUFFER_BIT);
     }
 
-    /// Enables blending of alpha textures. Disabled at end of frame.
-    fn enable_blending(&mut self) {
+    /// Changes the blending function. Reset at end of frame.
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.flush();
+        self.blend_mode = mode;
+
+        // Colors are already multiplied by alpha in the premultiplied pipeline
+        let (source, dest) = match (mode, self.premultiplied_alpha) {
+            (BlendMode::None, _) => {
+                gl::disable(gl::GL_BLEND);
+                return;
+            }
+            (BlendMode::Alpha, false) => (gl::GL_SRC_ALPHA, gl::GL_ONE_MINUS_SRC_ALPHA),
+            (BlendMode::Alpha, true) | (BlendMode::PremultipliedAlpha, _) => {
+                (gl::GL_ONE, gl::GL_ONE_MINUS_SRC_ALPHA)
+            }
+            (BlendMode::Additive, false) => (gl::GL_SRC_ALPHA, gl::GL_ONE),
+            (BlendMode::Additive, true) => (gl::GL_ONE, gl::GL_ONE),
+            (BlendMode::Multiply, false) => (gl::GL_DST_COLOR, gl::GL_ZERO),
+            (BlendMode::Multiply, true) => (gl::GL_DST_COLOR, gl::GL_ONE_MINUS_SRC_ALPHA),
+            (BlendMode::Screen, _) => (gl::GL_ONE, gl::GL_ONE_MINUS_SRC_COLOR),
+        };
+
         gl::enable(gl::GL_BLEND);
-        gl::blend_func(gl::GL_SRC_ALPHA, gl::GL_ONE_MINUS_SRC_ALPHA);
+        gl::blend_func(source, dest);
+    }
+
+    fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Switches the premultiplied alpha pipeline on or off.
+    fn set_premultiplied_alpha(&mut self, enabled: bool) {
+        self.flush();
+        self.premultiplied_alpha = enabled;
+
+        // Blending factors depend on the pipeline
+        let mode = self.blend_mode;
+        self.set_blend_mode(mode);
+    }
+
+    fn is_premultiplied_alpha(&self) -> bool {
+        self.premultiplied_alpha
     }
 
-    fn convert_image(&mut self, texture: &RgbaImage) -> Self::NativeTexture {
-        GlTexture::from_image(&texture)
+    fn convert_image_with_options(
+        &mut self,
+        texture: &RgbaImage,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture {
+        GlTexture::from_image(texture, self.premultiplied_alpha, options)
+            .with_release_queue(&self.releases)
+    }
+
+    fn convert_native_texture_with_options(
+        &mut self,
+        texture: Texture,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture {
+        GlTexture::from_texture(&texture, self.premultiplied_alpha, options)
+            .with_release_queue(&self.releases)
+    }
+
+    fn update_texture(
+        &mut self,
+        texture: &mut Self::NativeTexture,
+        region: &Rect,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        check_texture_update(
+            texture.get_width(),
+            texture.get_height(),
+            texture.get_format(),
+            region,
+            data,
+        )?;
+
+        // Draws waiting in the batch must see the old contents
+        self.flush();
+        texture.update(region, data);
+        Ok(())
     }
 
-    fn convert_native_texture(&mut self, texture: Texture) -> Self::NativeTexture {
-        GlTexture::from_texture(&texture)
+    fn resize_texture(&mut self, texture: &mut Self::NativeTexture, width: usize, height: usize) {
+        self.flush();
+        texture.resize(width, height);
     }
 
-    /// Returns the width of the screen.
+    /// Returns the width of the screen, or the current render target.
     fn get_width(&self) -> usize {
-        return self.size.width as usize;
+        match self.target {
+            Some(ref target) => target.width,
+            None => self.size.width as usize,
+        }
     }
 
-    /// Returns the height of the screen.
+    /// Returns the height of the screen, or the current render target.
     fn get_height(&self) -> usize {
-        return self.size.height as usize;
+        match self.target {
+            Some(ref target) => target.height,
+            None => self.size.height as usize,
+        }
     }
 
     /// Uses the specified image as a background. This is provided as several platforms
     /// have ways to accelerate this beyond OpenGL calls.
     fn set_background(&mut self, image: DynamicImage) {
+        // Headless drawers have no dispmanx layer to show a background on
+        let element = match self.context.layers {
+            Some(ref layers) => layers.bg_element,
+            None => return,
+        };
+
         match self.bg {
             Some(resource) => {
                 dispmanx::resource_delete(resource);
@@ -227,9 +462,6 @@ impl Drawer for PiDrawer {
 
         let bg_img = image.to_rgb();
 
-        // Resize the background to the correct size
-        //let size = Context::get_resolution();
-
         // Pad out the image, if required
         let target_width;
         let target_height;
@@ -275,8 +507,6 @@ impl Drawer for PiDrawer {
             height: target_height as i32,
         };
 
-        let element = self.context.bg_element;
-
         let bg_resource = dispmanx::resource_create(
             ImageType::RGB888,
             target_width as u32,
@@ -296,9 +526,8 @@ impl Drawer for PiDrawer {
 
         let update = dispmanx::update_start(10);
 
-        // Resize the element's src attr
-        //println!("Img dims: {} {}", target_width, target_height);
-
+        // Stretch the whole image over the display, which also keeps it covered if the
+        // display is resized later
         let src_rect = VCRect {
             x: 0,
             y: 0,
@@ -306,16 +535,23 @@ impl Drawer for PiDrawer {
             height: (target_height as i32) << 16,
         };
 
+        let display_rect = VCRect {
+            x: 0,
+            y: 0,
+            width: self.size.width as i32,
+            height: self.size.height as i32,
+        };
+
         dispmanx::element_change_attributes(
             update,
             element,
             (1 << 3) | (1 << 2),
-            0,                    // Ignored
-            255,                  // Ignored
-            &src_rect,            //&dest_rect,
-            &dest_rect,           //&src_rect,
-            0,                    // Ignored
-            Transform::NO_ROTATE, // Ignored
+            0,   // Ignored
+            255, // Ignored
+            &display_rect,
+            &src_rect,
+            0,                              // Ignored
+            dispmanx::Transform::NO_ROTATE, // Ignored
         );
 
         if dispmanx::element_change_source(update, element, bg_resource) {
@@ -330,8 +566,9 @@ impl Drawer for PiDrawer {
     }
 
     /// Sets the brightness of the screen.
-    fn set_brightness(&mut self, val: u8) -> ::std::io::Result<()> {
-        set_brightness(val)
+    fn set_brightness(&mut self, val: u8) -> Result<(), Error> {
+        set_brightness(val)?;
+        Ok(())
     }
 
     /// Draws a texture to the screen, with a specified set of vertices to draw to, a UV
@@ -343,47 +580,217 @@ impl Drawer for PiDrawer {
         colors: &[f32],
         uv: &[f32],
     ) {
-        self.configure_state(DrawState::Textured);
-
-        self.color.set_data(colors);
-        self.vertex.set_data(vertices);
-        self.uv.set_data(uv);
+        if self.batch_texture != Some(texture.get_id()) {
+            self.flush();
+        }
 
-        texture.bind_texture(gl::GL_TEXTURE_2D);
+        self.configure_state(DrawState::Textured);
 
-        gl::draw_arrays(
-            gl::GL_TRIANGLE_STRIP,
-            0,
-            (vertices.len() / 2) as gl::GLsizei,
-        );
+        self.batch_texture = Some(texture.get_id());
+        self.batch_premultiplied = texture.is_premultiplied();
+        self.batch_alpha = texture.get_format() == PixelFormat::Alpha8;
+        self.batch.push_strip(vertices, colors, uv);
     }
 
     /// Draws a set of colored vertices to the screen, with a specified color array.
     fn draw_colored_vertices(&mut self, vertices: &[f32], colors: &[f32]) {
         self.configure_state(DrawState::Colored);
 
-        self.vertex.set_data(vertices);
-        self.color.set_data(colors);
+        self.batch_texture = None;
+        self.batch.push_strip(vertices, colors, &[]);
+    }
 
-        gl::draw_arrays(
-            gl::GL_TRIANGLE_STRIP,
-            0,
-            (vertices.len() / 2) as gl::GLsizei,
-        );
+    /// Submits all batched draws in a single draw call.
+    fn flush(&mut self) {
+        if self.batch.is_empty() {
+            self.delete_released_textures();
+            return;
+        }
+
+        self.vertex.set_data(&self.batch.vertices);
+        self.color.set_data(&self.batch.colors);
+
+        if let Some(texture) = self.batch_texture {
+            self.uv.set_data(&self.batch.uv);
+            gl::uniform1i(
+                self.uniform_textured_premultiply,
+                self.premultiplied_alpha as gl::GLint,
+            );
+            gl::uniform1i(
+                self.uniform_textured_premultiplied_tex,
+                self.batch_premultiplied as gl::GLint,
+            );
+            gl::uniform1i(
+                self.uniform_textured_alpha_tex,
+                self.batch_alpha as gl::GLint,
+            );
+            gl::bind_texture(gl::GL_TEXTURE_2D, texture);
+        } else {
+            gl::uniform1i(
+                self.uniform_colored_premultiply,
+                self.premultiplied_alpha as gl::GLint,
+            );
+        }
+
+        gl::draw_arrays(gl::GL_TRIANGLES, 0, self.batch.len() as gl::GLsizei);
+
+        self.batch.clear();
+        self.delete_released_textures();
     }
 
     fn get_transition_count(&self) -> usize {
         self.transitions
     }
 
-    /// Creates a new drawer.
-    fn new(title: &str, width: u32, height: u32) -> Result<Self, String> {
-        let context = Context::build()?;
+    /// Combines the specified transform with the current one.
+    fn push_transform(&mut self, transform: &Transform) {
+        self.transforms.push(transform);
+    }
+
+    /// Restores the transform which was in effect before the last push.
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+
+    /// Returns the transform which is currently applied to draws.
+    fn get_transform(&self) -> Transform {
+        self.transforms.get()
+    }
+
+    /// Restricts following draws to the specified rectangle.
+    fn push_clip(&mut self, rect: &Rect) {
+        let rect = self.get_transform().bounding_rect(rect);
+        self.clips.push(&rect);
+        self.apply_clip();
+    }
+
+    /// Restores the clip which was in effect before the last push.
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+        self.apply_clip();
+    }
+
+    /// Returns the area which draws are currently restricted to.
+    fn get_clip(&self) -> Option<Rect> {
+        self.clips.get()
+    }
+
+    /// Creates a new texture, backed by a framebuffer object.
+    fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderTarget<Self::NativeTexture>, Error> {
+        // Creating the framebuffer changes which one is bound
+        self.flush();
+        let texture =
+            GlTexture::new_target(width as usize, height as usize, self.premultiplied_alpha);
+        self.bind_output();
+
+        Ok(RenderTarget::new(
+            texture?.with_release_queue(&self.releases),
+        ))
+    }
+
+    /// Redirects following draws into the specified target, or the screen.
+    fn set_render_target(&mut self, target: Option<&RenderTarget<Self::NativeTexture>>) {
+        self.flush();
+
+        self.target = target.map(|target| {
+            let texture = target.get_texture();
+
+            BoundTarget {
+                framebuffer: texture
+                    .get_framebuffer()
+                    .expect("Render target has no framebuffer")
+                    .get_id(),
+                width: texture.get_width(),
+                height: texture.get_height(),
+            }
+        });
+
+        self.bind_output();
+    }
+
+    /// Reads back the framebuffer, or the current render target. Must be called before
+    /// `end()`, as the contents of the back buffer are undefined once it has been swapped.
+    fn capture(&mut self) -> RgbaImage {
+        self.flush();
+
+        let width = self.get_width() as u32;
+        let height = self.get_height() as u32;
+
+        let pixels = gl::read_pixels(
+            0,
+            0,
+            width as gl::GLsizei,
+            height as gl::GLsizei,
+            gl::GL_RGBA,
+            gl::GL_UNSIGNED_BYTE,
+        );
+
+        let image = RgbaImage::from_raw(width, height, pixels)
+            .expect("Framebuffer did not match its own size");
+
+        // OpenGL returns the bottom row first, but render targets are already flipped
+        if self.target.is_some() {
+            image
+        } else {
+            imageops::flip_vertical(&image)
+        }
+    }
+
+    /// Creates a new drawer covering the whole display. There are no windows on the Pi, so
+    /// only vsync and multisampling are honoured.
+    fn with_config(config: DrawerConfig) -> Result<Self, Error> {
+        let context = Context::build(config.vsync, config.samples)?;
+
+        let size = Context::get_resolution()?;
+
+        let mut config_warnings = Vec::new();
+
+        if !config.fullscreen && (config.width != size.width || config.height != size.height) {
+            config_warnings.push(format!(
+                "Requested size of {}x{} was ignored, as the Pi always draws to the whole \
+                 {}x{} display",
+                config.width, config.height, size.width, size.height
+            ));
+        }
+
+        if config.icon.is_some() {
+            config_warnings.push("Window icon was ignored, as the Pi has no windows".into());
+        }
+
+        if config.min_size.is_some() {
+            config_warnings.push("Minimum size was ignored, as the Pi has no windows".into());
+        }
+
+        let mut drawer = PiDrawer::from_context(context, size)?;
+        drawer.config_warnings = config_warnings;
+
+        Ok(drawer)
+    }
+
+    fn config_warnings(&self) -> &[String] {
+        &self.config_warnings
+    }
+
+    /// Creates a new drawer, drawing into a offscreen pbuffer.
+    fn new_headless(width: u32, height: u32) -> Result<Self, Error> {
+        let context = Context::build_headless(width, height)?;
 
-        let size = Context::get_resolution();
+        PiDrawer::from_context(context, GraphicsDisplaySize { width, height })
+    }
+}
 
+impl PiDrawer {
+    /// Finishes setting up a drawer, once the EGL context is current.
+    fn from_context(context: Context, size: GraphicsDisplaySize) -> Result<Self, Error> {
         gl::viewport(0, 0, size.width as i32, size.height as i32);
 
+        // Textures with 1 or 3 bytes per pixel don't have rows aligned to 4 bytes
+        gl::pixel_storei(gl::GL_UNPACK_ALIGNMENT, 1);
+
         let vertex_vbo = GLVBO::new();
         let color_vbo = GLVBO::new();
         let uv_vbo = GLVBO::new();
@@ -396,6 +803,7 @@ impl Drawer for PiDrawer {
         colored_shader.use_program();
         let attr_colored_vertex = colored_shader.get_attribute("input_vertex");
         let attr_colored_color = colored_shader.get_attribute("input_color");
+        let uniform_colored_premultiply = colored_shader.get_uniform("premultiply");
 
         let textured_shader = GLSLShader::create_shader(
             include_bytes!("../../../res/pi_shaders/tex.vert"),
@@ -406,6 +814,9 @@ impl Drawer for PiDrawer {
         let attr_textured_vertex = textured_shader.get_attribute("input_vertex");
         let attr_textured_color = textured_shader.get_attribute("input_color");
         let attr_textured_uv = textured_shader.get_attribute("input_uv");
+        let uniform_textured_premultiply = textured_shader.get_uniform("premultiply");
+        let uniform_textured_premultiplied_tex = textured_shader.get_uniform("premultiplied_tex");
+        let uniform_textured_alpha_tex = textured_shader.get_uniform("alpha_tex");
 
         Ok(PiDrawer {
             context,
@@ -421,8 +832,24 @@ impl Drawer for PiDrawer {
             attr_textured_color,
             uv: uv_vbo,
             attr_textured_uv,
+            uniform_colored_premultiply,
+            uniform_textured_premultiply,
+            uniform_textured_premultiplied_tex,
+            uniform_textured_alpha_tex,
             bg: None,
+            batch: Batch::new(),
+            batch_texture: None,
+            batch_premultiplied: false,
+            releases: ReleaseQueue::default(),
+            batch_alpha: false,
+            premultiplied_alpha: false,
+            transforms: TransformStack::new(),
+            clips: ClipStack::new(),
+            blend_mode: BlendMode::None,
+            target: None,
+            resize_error: None,
             transitions: 0,
+            config_warnings: Vec::new(),
         })
     }
 }
@@ -0,0 +1,63 @@
+//! Manages OpenGLES framebuffer objects.
+//!
+use opengles::glesv2 as gl;
+
+use crate::render::pi::texture::GlTexture;
+use crate::Error;
+
+pub struct GLFramebuffer {
+    ptr: gl::GLuint,
+}
+
+impl GLFramebuffer {
+    /// Binds this framebuffer, redirecting all drawing into its attached texture.
+    pub fn bind(&self) {
+        gl::bind_framebuffer(gl::GL_FRAMEBUFFER, self.ptr)
+    }
+
+    /// Returns the raw OpenGL name of this framebuffer.
+    pub fn get_id(&self) -> gl::GLuint {
+        self.ptr
+    }
+
+    /// Binds the default (EGL surface) framebuffer.
+    pub fn unbind() {
+        gl::bind_framebuffer(gl::GL_FRAMEBUFFER, 0)
+    }
+
+    /// Creates a new framebuffer, with the specified texture as its color attachment.
+    /// The texture must outlive this framebuffer.
+    pub fn new(texture: &GlTexture) -> Result<Self, Error> {
+        let framebuffer = GLFramebuffer {
+            ptr: gl::gen_framebuffers(1)[0],
+        };
+        framebuffer.bind();
+
+        gl::framebuffer_texture_2d(
+            gl::GL_FRAMEBUFFER,
+            gl::GL_COLOR_ATTACHMENT0,
+            gl::GL_TEXTURE_2D,
+            texture.get_id(),
+            0,
+        );
+
+        let status = gl::check_framebuffer_status(gl::GL_FRAMEBUFFER);
+
+        GLFramebuffer::unbind();
+
+        if status != gl::GL_FRAMEBUFFER_COMPLETE {
+            return Err(Error::Texture(format!(
+                "Framebuffer is incomplete: {:#x}",
+                status
+            )));
+        }
+
+        Ok(framebuffer)
+    }
+}
+
+impl Drop for GLFramebuffer {
+    fn drop(&mut self) {
+        gl::delete_framebuffers(&[self.ptr]);
+    }
+}
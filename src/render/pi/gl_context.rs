@@ -16,12 +16,10 @@ use videocore::bcm_host::GraphicsDisplaySize;
 
 use std::ptr;
 
-pub struct Context {
-    pub config: EGLConfig,
-    pub context: EGLContext,
-    pub display: EGLDisplay,
-    pub surface: EGLSurface,
+use crate::Error;
 
+/// The dispmanx elements used to present the EGL surface (and background) on screen.
+pub struct DisplayLayers {
     window: Box<Window>,
     pub dispman_display: DisplayHandle,
     pub update: UpdateHandle,
@@ -30,10 +28,21 @@ pub struct Context {
     pub bg_element: ElementHandle,
 }
 
+pub struct Context {
+    pub config: EGLConfig,
+    pub context: EGLContext,
+    pub display: EGLDisplay,
+    pub surface: EGLSurface,
+
+    /// Missing if this context is headless.
+    pub layers: Option<DisplayLayers>,
+}
+
 impl Context {
     /// Returns the screen resolution of the device.
-    pub fn get_resolution() -> GraphicsDisplaySize {
-        bcm_host::graphics_get_display_size(0).unwrap()
+    pub fn get_resolution() -> Result<GraphicsDisplaySize, Error> {
+        bcm_host::graphics_get_display_size(0)
+            .ok_or_else(|| Error::Context("Failed to get display size".into()))
     }
 
     /// Swaps GPU buffers.
@@ -41,7 +50,9 @@ impl Context {
         egl::swap_buffers(self.display, self.surface)
     }
 
-    pub fn build() -> Result<Self, String> {
+    /// Builds a context which covers the whole display, optionally waiting for vsync and
+    /// multisampling each pixel with the specified number of samples (0 to disable).
+    pub fn build(vsync: bool, samples: u16) -> Result<Self, Error> {
         // first thing to do is initialize the broadcom host (when doing any graphics on RPi)
         bcm_host::init();
 
@@ -52,10 +63,10 @@ impl Context {
         let update = dispmanx::update_start(0);
 
         // get screen resolution (same display number as display_open()
-        let dimensions: Result<GraphicsDisplaySize, String> =
+        let dimensions: Result<GraphicsDisplaySize, Error> =
             match bcm_host::graphics_get_display_size(0) {
                 Some(x) => Ok(x),
-                None => Err("bcm_host::init() did not succeed".into()),
+                None => Err(Error::Context("bcm_host::init() did not succeed".into())),
             };
         let dimensions = dimensions?;
 
@@ -137,6 +148,184 @@ impl Context {
             height: dimensions.height as i32,
         });
 
+        let (egl_display, egl_config, egl_context) =
+            Context::create_egl(egl::EGL_WINDOW_BIT, samples)?;
+
+        // create surface
+        let egl_surface: Result<EGLSurface, Error> = match egl::create_window_surface(
+            egl_display,
+            egl_config,
+            window.as_mut() as *mut _ as EGLNativeDisplayType,
+            &[],
+        ) {
+            Some(x) => Ok(x),
+            None => Err(Error::Context("Failed to create EGL surface".into())),
+        };
+        let egl_surface: EGLSurface = egl_surface?;
+
+        // set current context
+        if !egl::make_current(egl_display, egl_surface, egl_surface, egl_context) {
+            return Err(Error::Context("Failed to make EGL current context".into()));
+        }
+
+        // add a vsync/swap interval
+        if !egl::swap_interval(egl_display, vsync as i32) {
+            return Err(Error::Context("Failed to setup swapping".into()));
+        }
+
+        Ok(Self {
+            config: egl_config,
+            context: egl_context,
+            display: egl_display,
+            surface: egl_surface,
+
+            layers: Some(DisplayLayers {
+                window,
+                dispman_display: display,
+                update,
+                element,
+
+                bg_element,
+            }),
+        })
+    }
+
+    /// Resizes the dispmanx elements and EGL surface to cover a display of the specified
+    /// size, such as after the resolution has changed. Does nothing for headless contexts.
+    ///
+    /// If the new surface can't be created, the context is left without one, and buffers
+    /// can't be swapped until a later resize succeeds.
+    pub fn resize(&mut self, size: &GraphicsDisplaySize) -> Result<(), Error> {
+        let layers = match self.layers {
+            Some(ref mut layers) => layers,
+            None => return Ok(()),
+        };
+
+        let dest_rect = Rect {
+            x: 0,
+            y: 0,
+            width: size.width as i32,
+            height: size.height as i32,
+        };
+
+        let src_rect = Rect {
+            x: 0,
+            y: 0,
+            width: (size.width as i32) << 16,
+            height: (size.height as i32) << 16,
+        };
+
+        let update = dispmanx::update_start(0);
+
+        // The background keeps its own source, so is just stretched over the new area
+        dispmanx::element_change_attributes(
+            update,
+            layers.bg_element,
+            1 << 2,
+            0,
+            255,
+            &dest_rect,
+            &src_rect,
+            0,
+            Transform::NO_ROTATE,
+        );
+
+        dispmanx::element_change_attributes(
+            update,
+            layers.element,
+            (1 << 3) | (1 << 2),
+            0,
+            255,
+            &dest_rect,
+            &src_rect,
+            0,
+            Transform::NO_ROTATE,
+        );
+
+        if dispmanx::update_submit_sync(update) {
+            return Err(Error::Context("Failed to resize display elements".into()));
+        }
+
+        layers.window.width = size.width as i32;
+        layers.window.height = size.height as i32;
+
+        // EGL only reads the size of a window when a surface is created for it
+        egl::make_current(
+            self.display,
+            egl::EGL_NO_SURFACE,
+            egl::EGL_NO_SURFACE,
+            egl::EGL_NO_CONTEXT,
+        );
+        if self.surface != egl::EGL_NO_SURFACE {
+            egl::destroy_surface(self.display, self.surface);
+        }
+
+        // Until the new surface exists there is nothing to draw into, or to destroy when
+        // dropped. Resizing again creates it, if this attempt fails.
+        self.surface = egl::EGL_NO_SURFACE;
+        self.surface = match egl::create_window_surface(
+            self.display,
+            self.config,
+            layers.window.as_mut() as *mut _ as EGLNativeDisplayType,
+            &[],
+        ) {
+            Some(x) => x,
+            None => return Err(Error::Context("Failed to recreate EGL surface".into())),
+        };
+
+        if !egl::make_current(self.display, self.surface, self.surface, self.context) {
+            return Err(Error::Context("Failed to make EGL current context".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Builds a context which isn't displayed anywhere, drawing into a pbuffer of the
+    /// specified size instead.
+    pub fn build_headless(width: u32, height: u32) -> Result<Self, Error> {
+        bcm_host::init();
+
+        let (egl_display, egl_config, egl_context) = Context::create_egl(egl::EGL_PBUFFER_BIT, 0)?;
+
+        let surface_attr = [
+            egl::EGL_WIDTH,
+            width as i32,
+            egl::EGL_HEIGHT,
+            height as i32,
+            egl::EGL_NONE,
+        ];
+
+        // create surface
+        let egl_surface: Result<EGLSurface, Error> =
+            match egl::create_pbuffer_surface(egl_display, egl_config, &surface_attr) {
+                Some(x) => Ok(x),
+                None => Err(Error::Context(
+                    "Failed to create EGL pbuffer surface".into(),
+                )),
+            };
+        let egl_surface: EGLSurface = egl_surface?;
+
+        // set current context
+        if !egl::make_current(egl_display, egl_surface, egl_surface, egl_context) {
+            return Err(Error::Context("Failed to make EGL current context".into()));
+        }
+
+        Ok(Self {
+            config: egl_config,
+            context: egl_context,
+            display: egl_display,
+            surface: egl_surface,
+
+            layers: None,
+        })
+    }
+
+    /// Initialises EGL, returning a display, configuration and context which can be
+    /// used with the specified kind of surface and number of samples per pixel.
+    fn create_egl(
+        surface_type: i32,
+        samples: u16,
+    ) -> Result<(EGLDisplay, EGLConfig, EGLContext), Error> {
         // Create a EGL context
         let context_attr = [egl::EGL_CONTEXT_CLIENT_VERSION, 2, egl::EGL_NONE];
 
@@ -150,102 +339,76 @@ impl Context {
             egl::EGL_ALPHA_SIZE,
             8,
             egl::EGL_SURFACE_TYPE,
-            egl::EGL_WINDOW_BIT,
+            surface_type,
+            egl::EGL_SAMPLE_BUFFERS,
+            (samples > 0) as i32,
+            egl::EGL_SAMPLES,
+            i32::from(samples),
             egl::EGL_NONE,
         ];
 
         // get display
-        let egl_display: Result<EGLDisplay, String> =
+        let egl_display: Result<EGLDisplay, Error> =
             match egl::get_display(egl::EGL_DEFAULT_DISPLAY) {
                 Some(x) => Ok(x),
-                None => Err("Failed to get EGL display".into()),
+                None => Err(Error::Context("Failed to get EGL display".into())),
             };
         let egl_display: EGLDisplay = egl_display?;
 
         // init display
         if !egl::initialize(egl_display, &mut 0i32, &mut 0i32) {
-            return Err("Failed to initialize EGL".into());
+            return Err(Error::Context("Failed to initialize EGL".into()));
         }
 
         // choose first available configuration
-        let egl_config: Result<EGLConfig, String> =
+        let egl_config: Result<EGLConfig, Error> =
             match egl::choose_config(egl_display, &config_attr, 1) {
                 Some(x) => Ok(x),
-                None => Err("Failed to get EGL configuration".into()),
+                None => Err(Error::Context("Failed to get EGL configuration".into())),
             };
         let egl_config: EGLConfig = egl_config?;
 
         // bind opengl es api
         if !egl::bind_api(egl::EGL_OPENGL_ES_API) {
-            return Err("Failed to bind EGL OpenGL ES API".into());
+            return Err(Error::Context("Failed to bind EGL OpenGL ES API".into()));
         }
 
         // create egl context
-        let egl_context: Result<EGLContext, String> = match egl::create_context(
+        let egl_context: Result<EGLContext, Error> = match egl::create_context(
             egl_display,
             egl_config,
             egl::EGL_NO_CONTEXT,
             &context_attr,
         ) {
             Some(x) => Ok(x),
-            None => Err("Failed to create EGL context".into()),
+            None => Err(Error::Context("Failed to create EGL context".into())),
         };
         let egl_context: EGLContext = egl_context?;
 
-        // create surface
-        let egl_surface: Result<EGLSurface, String> = match egl::create_window_surface(
-            egl_display,
-            egl_config,
-            window.as_mut() as *mut _ as EGLNativeDisplayType,
-            &[],
-        ) {
-            Some(x) => Ok(x),
-            None => Err("Failed to create EGL surface".into()),
-        };
-        let egl_surface: EGLSurface = egl_surface?;
-
-        // set current context
-        if !egl::make_current(egl_display, egl_surface, egl_surface, egl_context) {
-            return Err("Failed to make EGL current context".into());
-        }
-
-        // add a vsync/swap interval
-        if !egl::swap_interval(egl_display, 1) {
-            return Err("Failed to setup swapping".into());
-        }
-
-        Ok(Self {
-            config: egl_config,
-            context: egl_context,
-            display: egl_display,
-            surface: egl_surface,
-
-            window,
-            dispman_display: display,
-            update,
-            element,
-
-            bg_element,
-        })
+        Ok((egl_display, egl_config, egl_context))
     }
 }
 
 impl Drop for Context {
     fn drop(&mut self) {
         println!("Context shutdown!");
-        egl::destroy_surface(self.display, self.surface);
+        if self.surface != egl::EGL_NO_SURFACE {
+            egl::destroy_surface(self.display, self.surface);
+        }
         egl::destroy_context(self.display, self.context);
         egl::terminate(self.display);
 
-        dispmanx::element_remove(self.update, self.element);
-        dispmanx::element_remove(self.update, self.bg_element);
-        dispmanx::update_submit_sync(self.update);
-        // "Update" cannot be deleted?
-
-        if !dispmanx::display_close(self.dispman_display) {
-            println!("Display shutdown successful.");
-        } else {
-            println!("Display shutdown failed.");
+        if let Some(ref layers) = self.layers {
+            dispmanx::element_remove(layers.update, layers.element);
+            dispmanx::element_remove(layers.update, layers.bg_element);
+            dispmanx::update_submit_sync(layers.update);
+            // "Update" cannot be deleted?
+
+            if !dispmanx::display_close(layers.dispman_display) {
+                println!("Display shutdown successful.");
+            } else {
+                println!("Display shutdown failed.");
+            }
         }
 
         bcm_host::deinit();
@@ -1,5 +1,6 @@
 //! A simple OpenGL renderer for the Pi, taking advantage of several framebuffer-y hacks.
 
+mod framebuffer;
 mod gl_context;
 
 mod shader;
@@ -2,6 +2,9 @@
 
 use opengles::glesv2 as gl;
 
+use crate::error::ShaderStage;
+use crate::Error;
+
 pub struct GLSLShader {
     program: gl::GLuint,
     vertex: gl::GLuint,
@@ -21,39 +24,62 @@ impl GLSLShader {
         gl::get_attrib_location(self.program, name)
     }
 
+    /// Returns the location of a uniform variable in this program.
+    pub fn get_uniform(&self, name: &str) -> gl::GLint {
+        gl::get_uniform_location(self.program, name)
+    }
+
     /// Creates a new shader.
-    /// Returns: Shader if compile succeeded, msg if failed.
-    pub fn create_shader(vertex: &[u8], frag: &[u8]) -> Result<GLSLShader, String> {
+    /// Returns: Shader if compile succeeded, the info log if failed.
+    pub fn create_shader(vertex: &[u8], frag: &[u8]) -> Result<GLSLShader, Error> {
+        let vert_shader = compile_shader(gl::GL_VERTEX_SHADER, ShaderStage::Vertex, vertex)?;
+        let frag_shader = match compile_shader(gl::GL_FRAGMENT_SHADER, ShaderStage::Fragment, frag)
+        {
+            Ok(shader) => shader,
+            Err(err) => {
+                gl::delete_shader(vert_shader);
+                return Err(err);
+            }
+        };
+
         // Create our shader program
         let program = gl::create_program();
-
-        // Create our vertex shader
-        let vert_shader = gl::create_shader(gl::GL_VERTEX_SHADER);
-
-        gl::shader_source(vert_shader, vertex);
-
-        gl::compile_shader(vert_shader);
         gl::attach_shader(program, vert_shader);
-
-        // Create our fragment shader
-        let frag_shader = gl::create_shader(gl::GL_FRAGMENT_SHADER);
-
-        gl::shader_source(frag_shader, frag);
-
-        gl::compile_shader(frag_shader);
         gl::attach_shader(program, frag_shader);
 
         // Compile and link
         gl::link_program(program);
 
+        let shader = GLSLShader {
+            program,
+            vertex: vert_shader,
+            fragment: frag_shader,
+        };
+
         match gl::get_program_info_log(program, 8192) {
-            Some(msg) => Err(msg),
-            None => Ok(GLSLShader {
-                program,
-                vertex: vert_shader,
-                fragment: frag_shader,
-            }),
+            Some(log) => Err(Error::ShaderLink { log }),
+            None => Ok(shader),
+        }
+    }
+}
+
+/// Compiles a single stage of a shader program, returning the info log if it fails.
+fn compile_shader(
+    kind: gl::GLenum,
+    stage: ShaderStage,
+    source: &[u8],
+) -> Result<gl::GLuint, Error> {
+    let shader = gl::create_shader(kind);
+
+    gl::shader_source(shader, source);
+    gl::compile_shader(shader);
+
+    match gl::get_shader_info_log(shader, 8192) {
+        Some(log) => {
+            gl::delete_shader(shader);
+            Err(Error::ShaderCompile { stage, log })
         }
+        None => Ok(shader),
     }
 }
 
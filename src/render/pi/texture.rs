@@ -2,73 +2,277 @@
 //!
 use opengles::glesv2 as gl;
 
+use crate::pos::Rect;
+use crate::render::next_texture_id;
+use crate::render::pi::framebuffer::GLFramebuffer;
+use crate::render::premultiply_pixels;
 use crate::render::Dimensions;
+use crate::render::PixelFormat;
 use crate::render::Texture;
+use crate::render::TextureFilter;
+use crate::render::TextureOptions;
+use crate::render::TextureWrap;
+use crate::render::UniqueTexture;
+use crate::Error;
+
+use std::sync::Arc;
+use std::sync::Mutex;
 
 use image::RgbaImage;
 
+/// Sets the sampling parameters of the bound texture, generating mipmaps if needed.
+/// Returns if mipmaps are in use.
+fn apply_options(width: usize, height: usize, options: &TextureOptions) -> bool {
+    // OpenGL ES 2 only supports mipmaps and wrapping on power of two textures
+    let power_of_two = width.is_power_of_two() && height.is_power_of_two();
+    let mipmaps = options.needs_mipmaps() && power_of_two;
+
+    let (min_filter, mag_filter) = match (options.filter, mipmaps) {
+        (TextureFilter::Nearest, false) => (gl::GL_NEAREST, gl::GL_NEAREST),
+        (TextureFilter::Nearest, true) => (gl::GL_NEAREST_MIPMAP_NEAREST, gl::GL_NEAREST),
+        (TextureFilter::Linear, false) | (TextureFilter::Trilinear, false) => {
+            (gl::GL_LINEAR, gl::GL_LINEAR)
+        }
+        (TextureFilter::Linear, true) => (gl::GL_LINEAR_MIPMAP_NEAREST, gl::GL_LINEAR),
+        (TextureFilter::Trilinear, true) => (gl::GL_LINEAR_MIPMAP_LINEAR, gl::GL_LINEAR),
+    };
+
+    let wrap = match options.wrap {
+        _ if !power_of_two => gl::GL_CLAMP_TO_EDGE,
+        TextureWrap::Clamp => gl::GL_CLAMP_TO_EDGE,
+        TextureWrap::Repeat => gl::GL_REPEAT,
+        TextureWrap::Mirror => gl::GL_MIRRORED_REPEAT,
+    };
+
+    gl::tex_parameteri(
+        gl::GL_TEXTURE_2D,
+        gl::GL_TEXTURE_MIN_FILTER,
+        min_filter as gl::GLint,
+    );
+    gl::tex_parameteri(
+        gl::GL_TEXTURE_2D,
+        gl::GL_TEXTURE_MAG_FILTER,
+        mag_filter as gl::GLint,
+    );
+
+    gl::tex_parameteri(gl::GL_TEXTURE_2D, gl::GL_TEXTURE_WRAP_S, wrap as gl::GLint);
+    gl::tex_parameteri(gl::GL_TEXTURE_2D, gl::GL_TEXTURE_WRAP_T, wrap as gl::GLint);
+
+    if mipmaps {
+        gl::generate_mipmap(gl::GL_TEXTURE_2D);
+    }
+
+    mipmaps
+}
+
+/// Returns the format and type which OpenGL ES stores pixels of the specified format with.
+fn gl_format(format: PixelFormat) -> (gl::GLenum, gl::GLenum) {
+    match format {
+        PixelFormat::Rgba8888 => (gl::GL_RGBA, gl::GL_UNSIGNED_BYTE),
+        PixelFormat::Rgb888 => (gl::GL_RGB, gl::GL_UNSIGNED_BYTE),
+        PixelFormat::Rgb565 => (gl::GL_RGB, gl::GL_UNSIGNED_SHORT_5_6_5),
+        PixelFormat::Rgba4444 => (gl::GL_RGBA, gl::GL_UNSIGNED_SHORT_4_4_4_4),
+        // Sampled as black, so the shader is told to treat these as white
+        PixelFormat::Alpha8 => (gl::GL_ALPHA, gl::GL_UNSIGNED_BYTE),
+        PixelFormat::Luminance8 => (gl::GL_LUMINANCE, gl::GL_UNSIGNED_BYTE),
+    }
+}
+
+/// Textures which have been dropped, but may still be used by draws which haven't been
+/// flushed yet. These are deleted by the drawer once it has flushed.
+pub(crate) type ReleaseQueue = Arc<Mutex<Vec<gl::GLuint>>>;
+
 pub struct GlTexture {
+    unique_id: u64,
     width: usize,
     height: usize,
     ptr: gl::GLuint,
+    format: PixelFormat,
+    premultiplied: bool,
+    options: TextureOptions,
+    mipmaps: bool,
+    // Set if this texture can be drawn into
+    framebuffer: Option<GLFramebuffer>,
+    // Where this texture is handed over to when dropped, so draws still waiting on it
+    releases: Option<ReleaseQueue>,
 }
 
 impl GlTexture {
-    /// Converts a RGBA byte array to a OpenGL reference.
-    fn from_bytes(bytes: &[u8], width: usize, height: usize) -> Self {
+    /// Converts a byte array of pixels to a OpenGL reference, optionally premultiplying it.
+    fn from_bytes(
+        bytes: &[u8],
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        premultiply: bool,
+        options: &TextureOptions,
+    ) -> Self {
+        let premultiply = premultiply && format.can_premultiply();
+
+        let premultiplied_bytes;
+        let bytes = if premultiply {
+            premultiplied_bytes = premultiply_pixels(bytes, format);
+            &premultiplied_bytes
+        } else {
+            bytes
+        };
+
+        let (data_format, data_type) = gl_format(format);
+
         let texture_ref: gl::GLuint = gl::gen_textures(1)[0];
         gl::bind_texture(gl::GL_TEXTURE_2D, texture_ref);
         gl::tex_image_2d(
             gl::GL_TEXTURE_2D,
             0 as gl::GLint,
-            gl::GL_RGBA as gl::GLint,
+            data_format as gl::GLint,
             width as gl::GLint,
             height as gl::GLint,
             0,
-            gl::GL_RGBA,
-            gl::GL_UNSIGNED_BYTE,
+            data_format,
+            data_type,
             &bytes,
         );
 
-        gl::tex_parameteri(
-            gl::GL_TEXTURE_2D,
-            gl::GL_TEXTURE_MIN_FILTER,
-            gl::GL_LINEAR as gl::GLint,
-        );
-        gl::tex_parameteri(
-            gl::GL_TEXTURE_2D,
-            gl::GL_TEXTURE_MAG_FILTER,
-            gl::GL_LINEAR as gl::GLint,
-        );
+        let mipmaps = apply_options(width, height, options);
+
+        return GlTexture {
+            unique_id: next_texture_id(),
+            width,
+            height,
+            ptr: texture_ref,
+            format,
+            premultiplied: premultiply,
+            options: *options,
+            mipmaps,
+            framebuffer: None,
+            releases: None,
+        };
+    }
+
+    /// Replaces the pixels in a region of this texture with a byte array in the format of
+    /// this texture, which must exactly fill the region. The bytes are premultiplied first
+    /// if this texture is.
+    pub fn update(&mut self, region: &Rect, bytes: &[u8]) {
+        if region.width <= 0 || region.height <= 0 {
+            return;
+        }
+
+        let premultiplied_bytes;
+        let bytes = if self.premultiplied {
+            premultiplied_bytes = premultiply_pixels(bytes, self.format);
+            &premultiplied_bytes
+        } else {
+            bytes
+        };
 
-        gl::tex_parameteri(
+        let (data_format, data_type) = gl_format(self.format);
+
+        gl::bind_texture(gl::GL_TEXTURE_2D, self.ptr);
+        gl::tex_sub_image_2d(
             gl::GL_TEXTURE_2D,
-            gl::GL_TEXTURE_WRAP_S,
-            gl::GL_CLAMP_TO_EDGE as gl::GLint,
+            0 as gl::GLint,
+            region.x as gl::GLint,
+            region.y as gl::GLint,
+            region.width as gl::GLint,
+            region.height as gl::GLint,
+            data_format,
+            data_type,
+            &bytes,
         );
-        gl::tex_parameteri(
+
+        if self.mipmaps {
+            gl::generate_mipmap(gl::GL_TEXTURE_2D);
+        }
+    }
+
+    /// Changes the size of this texture, keeping the same OpenGL texture and its sampling
+    /// options. The contents become fully transparent.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let (data_format, data_type) = gl_format(self.format);
+
+        gl::bind_texture(gl::GL_TEXTURE_2D, self.ptr);
+        gl::tex_image_2d(
             gl::GL_TEXTURE_2D,
-            gl::GL_TEXTURE_WRAP_T,
-            gl::GL_CLAMP_TO_EDGE as gl::GLint,
+            0 as gl::GLint,
+            data_format as gl::GLint,
+            width as gl::GLint,
+            height as gl::GLint,
+            0,
+            data_format,
+            data_type,
+            &vec![0u8; width * height * self.format.bytes_per_pixel()],
         );
 
-        gl::generate_mipmap(gl::GL_TEXTURE_2D);
+        // Wrapping and mipmaps depend on the size
+        self.mipmaps = apply_options(width, height, &self.options);
+        self.width = width;
+        self.height = height;
+    }
 
-        return GlTexture {
+    /// Creates a new, fully transparent texture which can be drawn into. Premultiplied
+    /// targets expect to be drawn into with the premultiplied alpha pipeline.
+    pub fn new_target(width: usize, height: usize, premultiplied: bool) -> Result<Self, Error> {
+        let mut texture = GlTexture::from_bytes(
+            &vec![0; width * height * 4],
             width,
             height,
-            ptr: texture_ref,
-        };
+            PixelFormat::Rgba8888,
+            false,
+            &TextureOptions::default(),
+        );
+        texture.premultiplied = premultiplied;
+        texture.framebuffer = Some(GLFramebuffer::new(&texture)?);
+        Ok(texture)
+    }
+
+    /// Converts a texture to a OpenGL reference, optionally premultiplying it.
+    pub fn from_texture(tex: &Texture, premultiply: bool, options: &TextureOptions) -> Self {
+        GlTexture::from_bytes(
+            &tex.tex_data,
+            tex.get_width(),
+            tex.get_height(),
+            tex.get_format(),
+            premultiply,
+            options,
+        )
+    }
+
+    /// Converts a image to a OpenGL reference, optionally premultiplying it.
+    pub fn from_image(tex: &RgbaImage, premultiply: bool, options: &TextureOptions) -> Self {
+        GlTexture::from_bytes(
+            tex.as_ref(),
+            tex.width() as usize,
+            tex.height() as usize,
+            PixelFormat::Rgba8888,
+            premultiply,
+            options,
+        )
+    }
+
+    /// Returns the layout of the pixels in this texture.
+    pub fn get_format(&self) -> PixelFormat {
+        self.format
     }
 
-    /// Converts a texture to a OpenGL reference.
-    pub fn from_texture(tex: &Texture) -> Self {
-        GlTexture::from_bytes(&tex.tex_data, tex.get_width(), tex.get_height())
+    /// Checks if the colors of this texture have been multiplied by their alpha.
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
     }
 
-    /// Converts a image to a OpenGL reference.
-    pub fn from_image(tex: &RgbaImage) -> Self {
-        GlTexture::from_bytes(tex.as_ref(), tex.width() as usize, tex.height() as usize)
+    /// Returns the raw OpenGL name of this texture.
+    pub fn get_id(&self) -> gl::GLuint {
+        self.ptr
+    }
+
+    /// Defers deleting this texture once dropped until the owner of the queue has flushed.
+    pub(crate) fn with_release_queue(mut self, releases: &ReleaseQueue) -> Self {
+        self.releases = Some(releases.clone());
+        self
+    }
+
+    /// Returns the framebuffer which draws into this texture, if this is a render target.
+    pub fn get_framebuffer(&self) -> Option<&GLFramebuffer> {
+        self.framebuffer.as_ref()
     }
 
     /// Binds this OpenGL texture. This struct must
@@ -90,8 +294,22 @@ impl Dimensions for GlTexture {
     }
 }
 
+impl UniqueTexture for GlTexture {
+    /// Returns the ID this texture was given when created.
+    fn get_unique_id(&self) -> u64 {
+        self.unique_id
+    }
+}
+
 impl Drop for GlTexture {
     fn drop(&mut self) {
+        if let Some(releases) = &self.releases {
+            if let Ok(mut releases) = releases.lock() {
+                releases.push(self.ptr);
+                return;
+            }
+        }
+
         gl::delete_textures(&[self.ptr]);
     }
 }
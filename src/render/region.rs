@@ -0,0 +1,73 @@
+//! Regions of textures, in texel coordinates.
+
+use crate::pos::Rect;
+
+/// A rectangle of texels within a texture, which can be mirrored when drawn.
+///
+/// ```rust
+/// use leafrender::pos::Rect;
+/// use leafrender::render::region::TextureRegion;
+///
+/// // The top-left 16x16 texels, facing the other way
+/// let region = TextureRegion::new(Rect::new(0, 0, 16, 16)).flip_horizontal();
+///
+/// let uv = region.get_uv(64, 32);
+/// assert_eq!(&uv[0..2], &[0.25, 0.0]);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct TextureRegion {
+    pub rect: Rect,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl TextureRegion {
+    /// Mirrors this region from left to right.
+    pub fn flip_horizontal(mut self) -> Self {
+        self.flip_x = !self.flip_x;
+        self
+    }
+
+    /// Mirrors this region from top to bottom.
+    pub fn flip_vertical(mut self) -> Self {
+        self.flip_y = !self.flip_y;
+        self
+    }
+
+    /// Returns UV coordinates for this region of a texture with the specified size, in the
+    /// same vertex order as `Drawer::rect_to_vertices`.
+    pub fn get_uv(&self, texture_width: usize, texture_height: usize) -> [f32; 12] {
+        let mut min_u = self.rect.x as f32 / texture_width as f32;
+        let mut max_u = (self.rect.x + self.rect.width) as f32 / texture_width as f32;
+        let mut min_v = self.rect.y as f32 / texture_height as f32;
+        let mut max_v = (self.rect.y + self.rect.height) as f32 / texture_height as f32;
+
+        if self.flip_x {
+            ::std::mem::swap(&mut min_u, &mut max_u);
+        }
+
+        if self.flip_y {
+            ::std::mem::swap(&mut min_v, &mut max_v);
+        }
+
+        [
+            min_u, min_v, min_u, max_v, max_u, max_v, // Triangle 1
+            min_u, min_v, max_u, min_v, max_u, max_v,
+        ]
+    }
+
+    /// Creates a region covering the specified texels, without any flipping.
+    pub fn new(rect: Rect) -> Self {
+        TextureRegion {
+            rect,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+}
+
+impl From<Rect> for TextureRegion {
+    fn from(rect: Rect) -> Self {
+        TextureRegion::new(rect)
+    }
+}
@@ -0,0 +1,136 @@
+//! Tessellates circles, ellipses, arcs and rounded rectangles into triangles.
+
+use crate::pos::Rect;
+use crate::render::line::arc_segments;
+use crate::render::line::push_triangle;
+
+use std::f32::consts::PI;
+
+/// Returns evenly spaced points along an ellipse, from one angle to another (in radians,
+/// clockwise on screen from the positive x axis), including both ends.
+pub(crate) fn ellipse_points(
+    center: (f32, f32),
+    radius_x: f32,
+    radius_y: f32,
+    start: f32,
+    end: f32,
+    segments: usize,
+) -> Vec<(f32, f32)> {
+    let step = (end - start) / segments as f32;
+
+    (0..=segments)
+        .map(|i| {
+            let angle = start + step * i as f32;
+            (
+                center.0 + angle.cos() * radius_x,
+                center.1 + angle.sin() * radius_y,
+            )
+        })
+        .collect()
+}
+
+/// Returns points around the outside of a rounded rectangle, clockwise on screen from the
+/// top end of the left edge. The first point is repeated at the end, to close the outline.
+/// Each corner is split into the specified number of segments.
+pub(crate) fn rounded_rect_points(
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    radius: f32,
+    segments: usize,
+) -> Vec<(f32, f32)> {
+    let radius = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+
+    let corners = [
+        (x + radius, y + radius, PI),
+        (x + width - radius, y + radius, PI * 1.5),
+        (x + width - radius, y + height - radius, 0.0),
+        (x + radius, y + height - radius, PI * 0.5),
+    ];
+
+    let mut points = Vec::with_capacity((segments + 1) * 4 + 1);
+    for &(corner_x, corner_y, start) in &corners {
+        points.extend(ellipse_points(
+            (corner_x, corner_y),
+            radius,
+            radius,
+            start,
+            start + PI / 2.0,
+            segments,
+        ));
+    }
+    points.push(points[0]);
+
+    points
+}
+
+/// Tessellates a filled rectangle.
+pub(crate) fn fill_rect(rect: &Rect) -> Vec<f32> {
+    let left = rect.x as f32;
+    let top = rect.y as f32;
+    let right = (rect.x + rect.width) as f32;
+    let bottom = (rect.y + rect.height) as f32;
+
+    let mut triangles = Vec::with_capacity(12);
+    push_triangle(&mut triangles, (left, top), (left, bottom), (right, bottom));
+    push_triangle(&mut triangles, (left, top), (right, bottom), (right, top));
+    triangles
+}
+
+/// Tessellates a filled ellipse, finely enough to look smooth once scaled by the specified
+/// amount.
+pub(crate) fn fill_ellipse(
+    center: (f32, f32),
+    radius_x: f32,
+    radius_y: f32,
+    scale: f32,
+) -> Vec<f32> {
+    let segments = arc_segments(radius_x.max(radius_y) * scale, 2.0 * PI);
+    let outline = ellipse_points(center, radius_x, radius_y, 0.0, 2.0 * PI, segments);
+    fill_fan(center, &outline)
+}
+
+/// Tessellates a filled rounded rectangle, finely enough to look smooth once scaled by the
+/// specified amount.
+pub(crate) fn fill_rounded_rect(rect: &Rect, radius: f32, scale: f32) -> Vec<f32> {
+    let segments = arc_segments(radius * scale, PI / 2.0);
+
+    let outline = rounded_rect_points(
+        rect.x as f32,
+        rect.y as f32,
+        rect.width as f32,
+        rect.height as f32,
+        radius,
+        segments,
+    );
+    let center = (
+        rect.x as f32 + rect.width as f32 / 2.0,
+        rect.y as f32 + rect.height as f32 / 2.0,
+    );
+    fill_fan(center, &outline)
+}
+
+/// Fills the area between a center point and an outline with triangles.
+pub(crate) fn fill_fan(center: (f32, f32), outline: &[(f32, f32)]) -> Vec<f32> {
+    let mut triangles = Vec::with_capacity(outline.len() * 6);
+
+    for pair in outline.windows(2) {
+        push_triangle(&mut triangles, center, pair[0], pair[1]);
+    }
+
+    triangles
+}
+
+/// Fills the band between two outlines, which must have the same number of points, with
+/// triangles.
+pub(crate) fn fill_band(outer: &[(f32, f32)], inner: &[(f32, f32)]) -> Vec<f32> {
+    let mut triangles = Vec::with_capacity(outer.len() * 12);
+
+    for i in 1..outer.len().min(inner.len()) {
+        push_triangle(&mut triangles, outer[i - 1], inner[i - 1], inner[i]);
+        push_triangle(&mut triangles, outer[i - 1], inner[i], outer[i]);
+    }
+
+    triangles
+}
@@ -0,0 +1,432 @@
+//! Implementation of a drawer which rasterizes on the CPU.
+
+use crate::render::check_texture_update;
+use crate::render::target::RenderTarget;
+use crate::render::BlendMode;
+use crate::render::Dimensions;
+use crate::render::Drawer;
+use crate::render::Texture;
+use crate::render::TextureOptions;
+
+use crate::pos::Rect;
+use crate::Error;
+
+use image::DynamicImage;
+use image::RgbaImage;
+
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+
+use crate::render::clip::ClipStack;
+use crate::render::config::DrawerConfig;
+use crate::render::software::raster::Framebuffer;
+use crate::render::software::raster::Vertex;
+use crate::render::software::texture::SoftwareTexture;
+use crate::render::transform::Transform;
+use crate::render::transform::TransformStack;
+
+#[derive(Ord, PartialOrd, Eq, PartialEq)]
+enum DrawState {
+    None,
+    Colored,
+    Textured,
+}
+
+/// Multiplies the color channels of a normalised RGBA color by its alpha.
+fn premultiply_color(color: &mut [f32; 4]) {
+    let alpha = color[3];
+    for channel in &mut color[0..3] {
+        *channel *= alpha;
+    }
+}
+
+pub struct SoftwareDrawer {
+    framebuffer: Framebuffer,
+
+    blend_mode: BlendMode,
+    premultiplied_alpha: bool,
+
+    // Background image
+    background: Option<SoftwareTexture>,
+
+    state: DrawState,
+
+    transforms: TransformStack,
+    clips: ClipStack,
+
+    // The render target being drawn into, and the screen while it is
+    target: Option<Rc<RefCell<Vec<u8>>>>,
+    screen: Option<Framebuffer>,
+
+    // Debugging
+    transition_count: usize,
+    config_warnings: Vec<String>,
+}
+
+impl SoftwareDrawer {
+    /// Tracks state changes, mirroring the shader switches of the OpenGL backends.
+    fn configure_state(&mut self, target: DrawState) {
+        if self.state != target {
+            self.transition_count += 1;
+            self.state = target;
+        }
+    }
+
+    /// Builds the vertex at the specified index of a set of vertex arrays.
+    fn build_vertex(&self, vertices: &[f32], colors: &[f32], uv: &[f32], index: usize) -> Vertex {
+        let (x, y) = self
+            .framebuffer
+            .to_pixel(vertices[index * 2], vertices[index * 2 + 1]);
+
+        let mut color = [1.0; 4];
+        color.copy_from_slice(&colors[index * 4..index * 4 + 4]);
+
+        let uv = if uv.len() >= index * 2 + 2 {
+            [uv[index * 2], uv[index * 2 + 1]]
+        } else {
+            [0.0, 0.0]
+        };
+
+        Vertex { x, y, color, uv }
+    }
+
+    /// Rasterizes a triangle strip, in the same way that OpenGL's TRIANGLE_STRIP would.
+    fn draw_strip<F>(&mut self, vertices: &[f32], colors: &[f32], uv: &[f32], shader: F)
+    where
+        F: Fn(&[f32; 4], &[f32; 2]) -> [f32; 4],
+    {
+        let count = (vertices.len() / 2).min(colors.len() / 4);
+
+        for i in 2..count {
+            let a = self.build_vertex(vertices, colors, uv, i - 2);
+            let b = self.build_vertex(vertices, colors, uv, i - 1);
+            let c = self.build_vertex(vertices, colors, uv, i);
+
+            let blend_mode = self.blend_mode;
+            let premultiplied = self.premultiplied_alpha;
+            self.framebuffer
+                .draw_triangle(a, b, c, blend_mode, premultiplied, &shader);
+        }
+    }
+
+    /// Returns the contents of the framebuffer as tightly packed RGBA rows, top row first.
+    pub fn get_pixels(&self) -> &[u8] {
+        &self.framebuffer.data
+    }
+}
+
+impl Drawer for SoftwareDrawer {
+    type NativeTexture = SoftwareTexture;
+
+    fn start(&mut self) {
+        self.transition_count = 0;
+        self.state = DrawState::None;
+        self.transforms.clear();
+
+        self.clips.clear();
+        self.set_render_target(None);
+
+        self.blend_mode = BlendMode::None;
+    }
+
+    /// Ends this frame.
+    fn end(&mut self) -> Result<(), Error> {
+        self.set_render_target(None);
+        self.configure_state(DrawState::None);
+        self.blend_mode = BlendMode::None;
+
+        Ok(())
+    }
+
+    /// Clears the framebuffer.
+    fn clear(&mut self, transparent: bool) {
+        if transparent {
+            self.framebuffer.fill([0, 0, 0, 0]);
+        } else {
+            self.framebuffer.fill([0, 0, 0, 255]);
+        }
+
+        // Draw our background here, if required
+        if transparent && self.target.is_none() && self.background.is_some() {
+            let tex = self.background.take().unwrap();
+            // Cover the whole screen, regardless of the current transform
+            self.draw_textured_vertices(
+                &tex,
+                &[
+                    -1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0, 1.0, -1.0,
+                ],
+            );
+            self.background = Some(tex);
+        }
+    }
+
+    /// Changes the blending function. Reset at end of frame.
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Switches the premultiplied alpha pipeline on or off.
+    fn set_premultiplied_alpha(&mut self, enabled: bool) {
+        self.premultiplied_alpha = enabled;
+    }
+
+    fn is_premultiplied_alpha(&self) -> bool {
+        self.premultiplied_alpha
+    }
+
+    fn convert_image_with_options(
+        &mut self,
+        texture: &RgbaImage,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture {
+        SoftwareTexture::from_image(texture, self.premultiplied_alpha, options)
+    }
+
+    fn convert_native_texture_with_options(
+        &mut self,
+        texture: Texture,
+        options: &TextureOptions,
+    ) -> Self::NativeTexture {
+        SoftwareTexture::from_texture(&texture, self.premultiplied_alpha, options)
+    }
+
+    fn update_texture(
+        &mut self,
+        texture: &mut Self::NativeTexture,
+        region: &Rect,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        check_texture_update(
+            texture.get_width(),
+            texture.get_height(),
+            texture.get_format(),
+            region,
+            data,
+        )?;
+
+        self.flush();
+        texture.update(region, data);
+        Ok(())
+    }
+
+    fn resize_texture(&mut self, texture: &mut Self::NativeTexture, width: usize, height: usize) {
+        self.flush();
+        texture.resize(width, height);
+    }
+
+    /// Returns the width of the framebuffer.
+    fn get_width(&self) -> usize {
+        self.framebuffer.width
+    }
+
+    /// Returns the height of the framebuffer.
+    fn get_height(&self) -> usize {
+        self.framebuffer.height
+    }
+
+    /// Uses the specified image as a background, drawn when clearing to transparent.
+    fn set_background(&mut self, image: DynamicImage) {
+        let image =
+            SoftwareTexture::from_image(&image.to_rgba(), false, &TextureOptions::default());
+        self.background = Some(image);
+    }
+
+    /// Sets the brightness of the screen.
+    fn set_brightness(&mut self, _val: u8) -> Result<(), Error> {
+        // NOOP
+        Ok(())
+    }
+
+    /// Draws a texture to the screen, with a specified set of vertices to draw to, a UV
+    /// to decode the image with, and a color to use as a base.
+    fn draw_textured_vertices_colored_uv(
+        &mut self,
+        texture: &Self::NativeTexture,
+        vertices: &[f32],
+        colors: &[f32],
+        uv: &[f32],
+    ) {
+        self.configure_state(DrawState::Textured);
+
+        let premultiply = self.premultiplied_alpha;
+        let premultiplied_texture = texture.is_premultiplied();
+
+        // Mirrors the textured fragment shader of the OpenGL backends
+        self.draw_strip(vertices, colors, uv, |color, uv| {
+            let mut texel = texture.sample(uv[0], uv[1]);
+            let mut color = *color;
+
+            if premultiply {
+                if !premultiplied_texture {
+                    premultiply_color(&mut texel);
+                }
+                premultiply_color(&mut color);
+            } else if premultiplied_texture && texel[3] > 0.0 {
+                let alpha = texel[3];
+                for channel in &mut texel[0..3] {
+                    *channel /= alpha;
+                }
+            }
+
+            [
+                texel[0] * color[0],
+                texel[1] * color[1],
+                texel[2] * color[2],
+                texel[3] * color[3],
+            ]
+        });
+    }
+
+    /// Draws a set of colored vertices to the screen, with a specified color array.
+    fn draw_colored_vertices(&mut self, vertices: &[f32], colors: &[f32]) {
+        self.configure_state(DrawState::Colored);
+
+        let premultiply = self.premultiplied_alpha;
+
+        self.draw_strip(vertices, colors, &[], |color, _| {
+            let mut color = *color;
+            if premultiply {
+                premultiply_color(&mut color);
+            }
+            color
+        });
+    }
+
+    fn get_transition_count(&self) -> usize {
+        self.transition_count
+    }
+
+    /// Combines the specified transform with the current one.
+    fn push_transform(&mut self, transform: &Transform) {
+        self.transforms.push(transform);
+    }
+
+    /// Restores the transform which was in effect before the last push.
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+
+    /// Returns the transform which is currently applied to draws.
+    fn get_transform(&self) -> Transform {
+        self.transforms.get()
+    }
+
+    /// Restricts following draws to the specified rectangle.
+    fn push_clip(&mut self, rect: &Rect) {
+        let rect = self.get_transform().bounding_rect(rect);
+        self.clips.push(&rect);
+        self.framebuffer.clip = self.clips.get();
+    }
+
+    /// Restores the clip which was in effect before the last push.
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+        self.framebuffer.clip = self.clips.get();
+    }
+
+    /// Returns the area which draws are currently restricted to.
+    fn get_clip(&self) -> Option<Rect> {
+        self.clips.get()
+    }
+
+    /// Creates a new texture, which can be drawn into.
+    fn create_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderTarget<Self::NativeTexture>, Error> {
+        Ok(RenderTarget::new(SoftwareTexture::empty(
+            width as usize,
+            height as usize,
+            self.premultiplied_alpha,
+        )))
+    }
+
+    /// Redirects following draws into the specified target, or the screen.
+    fn set_render_target(&mut self, target: Option<&RenderTarget<Self::NativeTexture>>) {
+        // Hand back the pixels of the previous target, and restore the screen
+        if let Some(previous) = self.target.take() {
+            let screen = self
+                .screen
+                .take()
+                .expect("Screen was not kept for render target");
+            let drawn = mem::replace(&mut self.framebuffer, screen);
+            *previous.borrow_mut() = drawn.data;
+        }
+
+        if let Some(target) = target {
+            let texture = target.get_texture();
+            let data = texture.get_data();
+
+            let framebuffer = Framebuffer::from_data(
+                texture.get_width(),
+                texture.get_height(),
+                data.borrow().clone(),
+            );
+
+            self.screen = Some(mem::replace(&mut self.framebuffer, framebuffer));
+            self.target = Some(data.clone());
+        }
+
+        self.framebuffer.clip = self.clips.get();
+    }
+
+    fn capture(&mut self) -> RgbaImage {
+        RgbaImage::from_raw(
+            self.framebuffer.width as u32,
+            self.framebuffer.height as u32,
+            self.framebuffer.data.clone(),
+        )
+        .expect("Framebuffer did not match its own size")
+    }
+
+    /// Creates a new drawer with a framebuffer of the specified size. No window is opened, so
+    /// only the size is used.
+    fn with_config(config: DrawerConfig) -> Result<Self, Error> {
+        let mut drawer = SoftwareDrawer::new_headless(config.width, config.height)?;
+
+        let unsupported = [
+            (config.fullscreen, "Fullscreen"),
+            (!config.resizable, "Disabling resizing"),
+            (!config.decorations, "Disabling decorations"),
+            (!config.vsync, "Disabling vsync"),
+            (config.samples > 0, "Multisampling"),
+            (config.icon.is_some(), "Window icons"),
+            (config.min_size.is_some(), "Minimum sizes"),
+        ];
+
+        drawer.config_warnings = unsupported
+            .iter()
+            .filter(|(requested, _)| *requested)
+            .map(|(_, option)| format!("{} isn't supported without a window", option))
+            .collect();
+
+        Ok(drawer)
+    }
+
+    fn config_warnings(&self) -> &[String] {
+        &self.config_warnings
+    }
+
+    /// Creates a new drawer with a framebuffer of the specified size.
+    fn new_headless(width: u32, height: u32) -> Result<Self, Error> {
+        Ok(SoftwareDrawer {
+            framebuffer: Framebuffer::new(width as usize, height as usize),
+            blend_mode: BlendMode::None,
+            premultiplied_alpha: false,
+            background: None,
+            state: DrawState::None,
+            transforms: TransformStack::new(),
+            clips: ClipStack::new(),
+            target: None,
+            screen: None,
+            transition_count: 0,
+            config_warnings: Vec::new(),
+        })
+    }
+}
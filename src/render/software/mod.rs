@@ -0,0 +1,7 @@
+//! A CPU-only renderer, rasterizing everything into an in-memory framebuffer. Useful where
+//! no GPU is available at all.
+
+mod raster;
+mod texture;
+
+pub mod drawer;
@@ -0,0 +1,349 @@
+//! Rasterizes triangles into a RGBA framebuffer.
+
+use crate::pos::Rect;
+use crate::render::BlendMode;
+
+/// A single vertex, already translated into framebuffer pixel coordinates.
+#[derive(Clone, Copy)]
+pub struct Vertex {
+    pub x: f32,
+    pub y: f32,
+    pub color: [f32; 4],
+    pub uv: [f32; 2],
+}
+
+/// A RGBA framebuffer, stored top row first.
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+    /// The area which writes are restricted to, if any.
+    pub clip: Option<Rect>,
+}
+
+/// Returns twice the signed area of the triangle (a, b, p).
+fn edge(a: &Vertex, b: &Vertex, px: f32, py: f32) -> f32 {
+    // Always work from the same end of an edge, so that two triangles sharing it get
+    // exactly opposite results, and no pixels fall through the gap between them
+    if (b.x, b.y) < (a.x, a.y) {
+        return -edge(b, a, px, py);
+    }
+
+    (b.x - a.x) * (py - a.y) - (b.y - a.y) * (px - a.x)
+}
+
+/// Decides if pixels lying exactly on an edge belong to this triangle. Two triangles sharing
+/// an edge walk it in opposite directions, so exactly one of them claims those pixels.
+fn owns_edge(a: &Vertex, b: &Vertex) -> bool {
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    dy > 0.0 || (dy == 0.0 && dx < 0.0)
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+impl Framebuffer {
+    /// Creates a new, fully transparent framebuffer.
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer::from_data(width, height, vec![0; width * height * 4])
+    }
+
+    /// Creates a new framebuffer from existing RGBA pixels.
+    pub fn from_data(width: usize, height: usize, data: Vec<u8>) -> Self {
+        Framebuffer {
+            width,
+            height,
+            data,
+            clip: None,
+        }
+    }
+
+    /// Returns the area which may be written to, as min x, min y, max x and max y.
+    fn get_bounds(&self) -> (usize, usize, usize, usize) {
+        match self.clip {
+            Some(ref clip) => {
+                let min_x = clip.x.max(0) as usize;
+                let min_y = clip.y.max(0) as usize;
+                let max_x = ((clip.x + clip.width).max(0) as usize).min(self.width);
+                let max_y = ((clip.y + clip.height).max(0) as usize).min(self.height);
+                (min_x, min_y, max_x, max_y)
+            }
+            None => (0, 0, self.width, self.height),
+        }
+    }
+
+    /// Sets every pixel within the clip to the specified color.
+    pub fn fill(&mut self, color: [u8; 4]) {
+        let (min_x, min_y, max_x, max_y) = self.get_bounds();
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let offset = (y * self.width + x) * 4;
+                self.data[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Converts OpenGL normalised device coordinates into pixel coordinates.
+    pub fn to_pixel(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            (x + 1.0) / 2.0 * self.width as f32,
+            (1.0 - y) / 2.0 * self.height as f32,
+        )
+    }
+
+    /// Writes a single color into the framebuffer, blending it with the existing color in
+    /// the same way as OpenGL's blending functions. Premultiplied colors use the factors of
+    /// the premultiplied alpha pipeline.
+    fn write_pixel(
+        &mut self,
+        x: usize,
+        y: usize,
+        color: [f32; 4],
+        blend_mode: BlendMode,
+        premultiplied: bool,
+    ) {
+        let offset = (y * self.width + x) * 4;
+        let pixel = &mut self.data[offset..offset + 4];
+
+        let alpha = color[3];
+        for (dest, src) in pixel.iter_mut().zip(color.iter()) {
+            let dest_value = f32::from(*dest) / 255.0;
+
+            let value = match (blend_mode, premultiplied) {
+                (BlendMode::None, _) => *src,
+                (BlendMode::Alpha, false) => src * alpha + dest_value * (1.0 - alpha),
+                (BlendMode::Alpha, true) | (BlendMode::PremultipliedAlpha, _) => {
+                    src + dest_value * (1.0 - alpha)
+                }
+                (BlendMode::Additive, false) => src * alpha + dest_value,
+                (BlendMode::Additive, true) => src + dest_value,
+                (BlendMode::Multiply, false) => src * dest_value,
+                (BlendMode::Multiply, true) => src * dest_value + dest_value * (1.0 - alpha),
+                (BlendMode::Screen, _) => src + dest_value * (1.0 - src),
+            };
+
+            *dest = to_byte(value);
+        }
+    }
+
+    /// Rasterizes a single triangle. The shader is given the interpolated color and UV for
+    /// each covered pixel, and returns the final color for that pixel.
+    pub fn draw_triangle<F>(
+        &mut self,
+        a: Vertex,
+        b: Vertex,
+        c: Vertex,
+        blend_mode: BlendMode,
+        premultiplied: bool,
+        shader: F,
+    ) where
+        F: Fn(&[f32; 4], &[f32; 2]) -> [f32; 4],
+    {
+        let area = edge(&a, &b, c.x, c.y);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+
+        // Keep a consistent winding so that the edge functions are positive inside
+        let (b, c, area) = if area < 0.0 {
+            (c, b, -area)
+        } else {
+            (b, c, area)
+        };
+
+        let (clip_min_x, clip_min_y, clip_max_x, clip_max_y) = self.get_bounds();
+
+        let min_x = (a.x.min(b.x).min(c.x).floor().max(0.0) as usize).max(clip_min_x);
+        let min_y = (a.y.min(b.y).min(c.y).floor().max(0.0) as usize).max(clip_min_y);
+        let max_x = (a.x.max(b.x).max(c.x).ceil().max(0.0) as usize).min(clip_max_x);
+        let max_y = (a.y.max(b.y).max(c.y).ceil().max(0.0) as usize).min(clip_max_y);
+
+        let owns_bc = owns_edge(&b, &c);
+        let owns_ca = owns_edge(&c, &a);
+        let owns_ab = owns_edge(&a, &b);
+
+        for y in min_y..max_y {
+            let py = y as f32 + 0.5;
+
+            for x in min_x..max_x {
+                let px = x as f32 + 0.5;
+
+                let weight_a = edge(&b, &c, px, py);
+                let weight_b = edge(&c, &a, px, py);
+                let weight_c = edge(&a, &b, px, py);
+
+                if weight_a < 0.0
+                    || weight_b < 0.0
+                    || weight_c < 0.0
+                    || (weight_a == 0.0 && !owns_bc)
+                    || (weight_b == 0.0 && !owns_ca)
+                    || (weight_c == 0.0 && !owns_ab)
+                {
+                    continue;
+                }
+
+                let weight_a = weight_a / area;
+                let weight_b = weight_b / area;
+                let weight_c = weight_c / area;
+
+                let mut color = [0.0; 4];
+                for (i, value) in color.iter_mut().enumerate() {
+                    *value = a.color[i] * weight_a + b.color[i] * weight_b + c.color[i] * weight_c;
+                }
+
+                let uv = [
+                    a.uv[0] * weight_a + b.uv[0] * weight_b + c.uv[0] * weight_c,
+                    a.uv[1] * weight_a + b.uv[1] * weight_b + c.uv[1] * weight_c,
+                ];
+
+                let output = shader(&color, &uv);
+                self.write_pixel(x, y, output, blend_mode, premultiplied);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32, y: f32, color: [f32; 4]) -> Vertex {
+        Vertex {
+            x,
+            y,
+            color,
+            uv: [0.0, 0.0],
+        }
+    }
+
+    /// Adds a little to every pixel that a triangle covers, so that pixels covered twice
+    /// can be told apart.
+    fn add_triangle(framebuffer: &mut Framebuffer, a: (f32, f32), b: (f32, f32), c: (f32, f32)) {
+        let color = [0.2, 0.2, 0.2, 1.0];
+        framebuffer.draw_triangle(
+            vertex(a.0, a.1, color),
+            vertex(b.0, b.1, color),
+            vertex(c.0, c.1, color),
+            BlendMode::Additive,
+            false,
+            |color, _| *color,
+        );
+    }
+
+    fn get_pixel(framebuffer: &Framebuffer, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * framebuffer.width + x) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&framebuffer.data[offset..offset + 4]);
+        pixel
+    }
+
+    #[test]
+    fn covers_pixels_with_centers_inside() {
+        let mut framebuffer = Framebuffer::new(8, 8);
+        add_triangle(&mut framebuffer, (2.0, 2.0), (6.0, 2.0), (6.0, 6.0));
+        add_triangle(&mut framebuffer, (2.0, 2.0), (6.0, 6.0), (2.0, 6.0));
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (2..6).contains(&y);
+                let expected = if inside { 51 } else { 0 };
+                assert_eq!(get_pixel(&framebuffer, x, y)[0], expected, "{}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn covers_shared_edges_once() {
+        // A fan around a point, with edges passing exactly through pixel centers
+        let center = (8.5, 8.5);
+        let rim = [
+            (0.5, 0.5),
+            (8.5, 0.0),
+            (16.0, 0.5),
+            (16.0, 8.5),
+            (16.0, 16.0),
+            (8.5, 16.0),
+            (0.5, 16.0),
+            (0.0, 8.5),
+        ];
+
+        let mut framebuffer = Framebuffer::new(16, 16);
+        for i in 0..rim.len() {
+            add_triangle(&mut framebuffer, center, rim[i], rim[(i + 1) % rim.len()]);
+        }
+
+        for y in 1..16 {
+            for x in 1..16 {
+                assert_eq!(get_pixel(&framebuffer, x, y)[0], 51, "{}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn skips_degenerate_triangles() {
+        let mut framebuffer = Framebuffer::new(8, 8);
+        add_triangle(&mut framebuffer, (0.0, 0.0), (4.0, 4.0), (8.0, 8.0));
+        add_triangle(&mut framebuffer, (0.0, 0.0), (f32::NAN, 4.0), (8.0, 0.0));
+
+        assert!(framebuffer.data.iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    fn only_writes_within_the_clip() {
+        let mut framebuffer = Framebuffer::new(8, 8);
+        framebuffer.clip = Some(Rect::new(2, 3, 4, 2));
+        add_triangle(&mut framebuffer, (-8.0, -8.0), (24.0, -8.0), (-8.0, 24.0));
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (3..5).contains(&y);
+                assert_eq!(
+                    get_pixel(&framebuffer, x, y)[0] != 0,
+                    inside,
+                    "{}, {}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interpolates_colors() {
+        let mut framebuffer = Framebuffer::new(16, 1);
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+
+        framebuffer.draw_triangle(
+            vertex(0.0, 0.0, red),
+            vertex(16.0, 0.0, blue),
+            vertex(0.0, 16.0, red),
+            BlendMode::None,
+            false,
+            |color, _| *color,
+        );
+
+        assert_eq!(get_pixel(&framebuffer, 0, 0), [247, 0, 8, 255]);
+        assert_eq!(get_pixel(&framebuffer, 15, 0), [8, 0, 247, 255]);
+    }
+
+    #[test]
+    fn blends_with_existing_pixels() {
+        let mut framebuffer = Framebuffer::new(1, 1);
+        framebuffer.fill([0, 0, 255, 255]);
+
+        framebuffer.draw_triangle(
+            vertex(-1.0, -1.0, [1.0, 0.0, 0.0, 0.5]),
+            vertex(4.0, -1.0, [1.0, 0.0, 0.0, 0.5]),
+            vertex(-1.0, 4.0, [1.0, 0.0, 0.0, 0.5]),
+            BlendMode::Alpha,
+            false,
+            |color, _| *color,
+        );
+
+        assert_eq!(get_pixel(&framebuffer, 0, 0), [128, 0, 128, 191]);
+    }
+}
@@ -0,0 +1,248 @@
+//! A holder for a CPU-side RGBA texture.
+
+use crate::pos::Rect;
+use crate::render::convert_pixels;
+use crate::render::next_texture_id;
+use crate::render::premultiply_alpha;
+use crate::render::Dimensions;
+use crate::render::PixelFormat;
+use crate::render::Texture;
+use crate::render::TextureFilter;
+use crate::render::TextureOptions;
+use crate::render::TextureWrap;
+use crate::render::UniqueTexture;
+
+use image::RgbaImage;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct SoftwareTexture {
+    unique_id: u64,
+    width: usize,
+    height: usize,
+    // Always RGBA, whatever format the texture was converted from. Shared with the drawer
+    // while this texture is a render target
+    data: Rc<RefCell<Vec<u8>>>,
+    format: PixelFormat,
+    premultiplied: bool,
+    options: TextureOptions,
+}
+
+/// Moves a texel coordinate which is outside of the texture back inside it.
+fn wrap(coord: isize, size: usize, mode: TextureWrap) -> usize {
+    let size = size as isize;
+
+    let coord = match mode {
+        TextureWrap::Clamp => coord.max(0).min(size - 1),
+        TextureWrap::Repeat => coord.rem_euclid(size),
+        TextureWrap::Mirror => {
+            let coord = coord.rem_euclid(size * 2);
+            if coord >= size {
+                size * 2 - 1 - coord
+            } else {
+                coord
+            }
+        }
+    };
+
+    coord as usize
+}
+
+/// Returns a single texel as normalised RGBA, wrapping coordinates outside of the texture.
+fn texel(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    x: isize,
+    y: isize,
+    mode: TextureWrap,
+) -> [f32; 4] {
+    let x = wrap(x, width, mode);
+    let y = wrap(y, height, mode);
+    let offset = (y * width + x) * 4;
+
+    [
+        f32::from(data[offset]) / 255.0,
+        f32::from(data[offset + 1]) / 255.0,
+        f32::from(data[offset + 2]) / 255.0,
+        f32::from(data[offset + 3]) / 255.0,
+    ]
+}
+
+impl SoftwareTexture {
+    /// Copies a byte array of pixels into a new texture, optionally premultiplying it.
+    fn from_bytes(
+        bytes: &[u8],
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        premultiply: bool,
+        options: &TextureOptions,
+    ) -> Self {
+        let data = convert_pixels(bytes, format, PixelFormat::Rgba8888);
+        let data = if premultiply {
+            premultiply_alpha(&data)
+        } else {
+            data
+        };
+
+        SoftwareTexture {
+            unique_id: next_texture_id(),
+            width,
+            height,
+            data: Rc::new(RefCell::new(data)),
+            format,
+            premultiplied: premultiply,
+            options: *options,
+        }
+    }
+
+    /// Creates a new, fully transparent texture. Premultiplied textures expect to be drawn
+    /// into with the premultiplied alpha pipeline.
+    pub fn empty(width: usize, height: usize, premultiplied: bool) -> Self {
+        SoftwareTexture::from_bytes(
+            &vec![0; width * height * 4],
+            width,
+            height,
+            PixelFormat::Rgba8888,
+            premultiplied,
+            &TextureOptions::default(),
+        )
+    }
+
+    /// Replaces the pixels in a region of this texture with a byte array in the format of
+    /// this texture, which must exactly fill the region. The bytes are premultiplied first
+    /// if this texture is.
+    pub fn update(&mut self, region: &Rect, bytes: &[u8]) {
+        if region.width <= 0 || region.height <= 0 {
+            return;
+        }
+
+        let bytes = convert_pixels(bytes, self.format, PixelFormat::Rgba8888);
+        let bytes = if self.premultiplied {
+            premultiply_alpha(&bytes)
+        } else {
+            bytes
+        };
+
+        let mut data = self.data.borrow_mut();
+        let row_length = region.width as usize * 4;
+
+        for (row, src) in bytes.chunks(row_length).enumerate() {
+            let y = region.y as usize + row;
+            let dest = (y * self.width + region.x as usize) * 4;
+            data[dest..dest + row_length].copy_from_slice(src);
+        }
+    }
+
+    /// Changes the size of this texture, keeping its sampling options. The contents become
+    /// fully transparent.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        *self.data.borrow_mut() = vec![0; width * height * 4];
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Returns the layout of the pixels this texture was converted from.
+    pub fn get_format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Checks if the colors of this texture have been multiplied by their alpha.
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    /// Returns the pixels of this texture, so that they can be drawn into.
+    pub(crate) fn get_data(&self) -> &Rc<RefCell<Vec<u8>>> {
+        &self.data
+    }
+
+    /// Converts a texture to a software reference, optionally premultiplying it.
+    pub fn from_texture(tex: &Texture, premultiply: bool, options: &TextureOptions) -> Self {
+        SoftwareTexture::from_bytes(
+            &tex.tex_data,
+            tex.get_width(),
+            tex.get_height(),
+            tex.get_format(),
+            premultiply,
+            options,
+        )
+    }
+
+    /// Converts a image to a software reference, optionally premultiplying it.
+    pub fn from_image(tex: &RgbaImage, premultiply: bool, options: &TextureOptions) -> Self {
+        SoftwareTexture::from_bytes(
+            tex.as_ref(),
+            tex.width() as usize,
+            tex.height() as usize,
+            PixelFormat::Rgba8888,
+            premultiply,
+            options,
+        )
+    }
+
+    /// Samples this texture at the specified UV coordinate, following the filter and wrap
+    /// mode it was created with. Mipmaps aren't generated, so trilinear filtering is the
+    /// same as linear filtering.
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        if self.width == 0 || self.height == 0 {
+            return [0.0; 4];
+        }
+
+        let data = self.data.borrow();
+        let (width, height) = (self.width, self.height);
+        let mode = self.options.wrap;
+
+        if self.options.filter == TextureFilter::Nearest {
+            let x = (u * width as f32).floor() as isize;
+            let y = (v * height as f32).floor() as isize;
+            return texel(&data, width, height, x, y, mode);
+        }
+
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+
+        let x0 = x0 as isize;
+        let y0 = y0 as isize;
+
+        let top_left = texel(&data, width, height, x0, y0, mode);
+        let top_right = texel(&data, width, height, x0 + 1, y0, mode);
+        let bottom_left = texel(&data, width, height, x0, y0 + 1, mode);
+        let bottom_right = texel(&data, width, height, x0 + 1, y0 + 1, mode);
+
+        let mut result = [0.0; 4];
+        for i in 0..4 {
+            let top = top_left[i] + (top_right[i] - top_left[i]) * fx;
+            let bottom = bottom_left[i] + (bottom_right[i] - bottom_left[i]) * fx;
+            result[i] = top + (bottom - top) * fy;
+        }
+
+        result
+    }
+}
+
+impl Dimensions for SoftwareTexture {
+    /// Returns the width of this texture.
+    fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of this texture.
+    fn get_height(&self) -> usize {
+        self.height
+    }
+}
+
+impl UniqueTexture for SoftwareTexture {
+    /// Returns the ID this texture was given when created.
+    fn get_unique_id(&self) -> u64 {
+        self.unique_id
+    }
+}
@@ -0,0 +1,469 @@
+//! Sprite sheets, which split a single texture into many frames, and animations which step
+//! through those frames over time.
+
+use crate::render::atlas::AtlasRegion;
+use crate::render::Dimensions;
+use crate::Error;
+
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::time::Duration;
+
+/// A texture split up into frames, which can be looked up by index or by name. Frames can be
+/// drawn with `Drawer::draw_region`.
+pub struct SpriteSheet<T> {
+    texture: T,
+    frames: Vec<AtlasRegion>,
+    durations: Vec<Option<Duration>>,
+    names: BTreeMap<String, usize>,
+    tags: BTreeMap<String, Range<usize>>,
+}
+
+impl<T: Dimensions> SpriteSheet<T> {
+    /// Splits a texture into a grid of equally sized frames, numbered left to right and then
+    /// top to bottom. Any space left over on the right or bottom edges is ignored.
+    pub fn from_grid(texture: T, frame_width: usize, frame_height: usize) -> Self {
+        let width = texture.get_width();
+        let height = texture.get_height();
+
+        let mut frames = Vec::new();
+        if frame_width > 0 && frame_height > 0 {
+            for row in 0..height / frame_height {
+                for column in 0..width / frame_width {
+                    frames.push(AtlasRegion::new(
+                        column * frame_width,
+                        row * frame_height,
+                        frame_width,
+                        frame_height,
+                        width,
+                        height,
+                    ));
+                }
+            }
+        }
+
+        SpriteSheet {
+            texture,
+            durations: vec![None; frames.len()],
+            frames,
+            names: BTreeMap::new(),
+            tags: BTreeMap::new(),
+        }
+    }
+
+    /// Reads frames from a JSON description of the texture, as exported by TexturePacker or
+    /// Aseprite. Both the "hash" and "array" layouts are supported. Frames are numbered in
+    /// the order they appear, and named after their filenames.
+    ///
+    /// Aseprite frame durations and tags are read if they are present. Trimmed frames are
+    /// drawn at their trimmed size, and rotated frames aren't supported.
+    pub fn from_json(texture: T, json: &str) -> Result<Self, Error> {
+        let root: Value = serde_json::from_str(json)
+            .map_err(|x| Error::InvalidData(format!("Bad JSON: {}", x)))?;
+
+        let entries: Vec<(Option<String>, &Value)> = match root.get("frames") {
+            Some(Value::Object(frames)) => frames
+                .iter()
+                .map(|(name, frame)| (Some(name.to_owned()), frame))
+                .collect(),
+            Some(Value::Array(frames)) => frames
+                .iter()
+                .map(|frame| {
+                    let name = frame
+                        .get("filename")
+                        .and_then(Value::as_str)
+                        .map(str::to_owned);
+                    (name, frame)
+                })
+                .collect(),
+            _ => {
+                return Err(Error::InvalidData(
+                    "Missing \"frames\" in sprite sheet".into(),
+                ))
+            }
+        };
+
+        let width = texture.get_width();
+        let height = texture.get_height();
+
+        let mut frames = Vec::with_capacity(entries.len());
+        let mut durations = Vec::with_capacity(entries.len());
+        let mut names = BTreeMap::new();
+
+        for (i, (name, entry)) in entries.into_iter().enumerate() {
+            if entry.get("rotated").and_then(Value::as_bool) == Some(true) {
+                return Err(Error::InvalidData(format!(
+                    "Frame {} is rotated, which isn't supported",
+                    i
+                )));
+            }
+
+            let rect = entry.get("frame").ok_or_else(|| {
+                Error::InvalidData(format!("Frame {} is missing its \"frame\" rectangle", i))
+            })?;
+
+            let field = |key: &str| {
+                rect.get(key)
+                    .and_then(Value::as_u64)
+                    .and_then(|x| usize::try_from(x).ok())
+                    .ok_or_else(|| {
+                        Error::InvalidData(format!("Frame {} is missing \"{}\"", i, key))
+                    })
+            };
+
+            let (x, y, frame_width, frame_height) =
+                (field("x")?, field("y")?, field("w")?, field("h")?);
+
+            let inside = match (x.checked_add(frame_width), y.checked_add(frame_height)) {
+                (Some(right), Some(bottom)) => right <= width && bottom <= height,
+                _ => false,
+            };
+
+            if !inside {
+                return Err(Error::InvalidData(format!(
+                    "Frame {} lies outside of the texture",
+                    i
+                )));
+            }
+
+            frames.push(AtlasRegion::new(
+                x,
+                y,
+                frame_width,
+                frame_height,
+                width,
+                height,
+            ));
+            durations.push(
+                entry
+                    .get("duration")
+                    .and_then(Value::as_u64)
+                    .map(Duration::from_millis),
+            );
+
+            if let Some(name) = name {
+                names.insert(name, i);
+            }
+        }
+
+        let mut tags = BTreeMap::new();
+        let tag_list = root
+            .get("meta")
+            .and_then(|meta| meta.get("frameTags"))
+            .and_then(Value::as_array);
+
+        for tag in tag_list.into_iter().flatten() {
+            let name = tag.get("name").and_then(Value::as_str);
+            let from = tag.get("from").and_then(Value::as_u64);
+            let to = tag.get("to").and_then(Value::as_u64);
+
+            if let (Some(name), Some(from), Some(to)) = (name, from, to) {
+                // Anything which doesn't fit in a usize is past the last frame anyway
+                let from = usize::try_from(from).unwrap_or(usize::MAX);
+                let end = usize::try_from(to)
+                    .ok()
+                    .and_then(|to| to.checked_add(1))
+                    .unwrap_or(usize::MAX);
+
+                let range = from..end;
+                if range.end > frames.len() || range.start >= range.end {
+                    return Err(Error::InvalidData(format!(
+                        "Tag {:?} refers to missing frames",
+                        name
+                    )));
+                }
+
+                tags.insert(name.to_owned(), range);
+            }
+        }
+
+        Ok(SpriteSheet {
+            texture,
+            frames,
+            durations,
+            names,
+            tags,
+        })
+    }
+}
+
+impl<T> SpriteSheet<T> {
+    /// Returns the texture which frames should be drawn from.
+    pub fn get_texture(&self) -> &T {
+        &self.texture
+    }
+
+    /// Returns the frame with the specified index.
+    pub fn get_frame(&self, index: usize) -> Option<AtlasRegion> {
+        self.frames.get(index).cloned()
+    }
+
+    /// Returns the frame with the specified name.
+    pub fn get_named_frame(&self, name: &str) -> Option<AtlasRegion> {
+        self.get_index(name).and_then(|index| self.get_frame(index))
+    }
+
+    /// Returns the index of the frame with the specified name.
+    pub fn get_index(&self, name: &str) -> Option<usize> {
+        self.names.get(name).cloned()
+    }
+
+    /// Returns how long the frame with the specified index should be shown for, if the
+    /// sheet specifies it.
+    pub fn get_duration(&self, index: usize) -> Option<Duration> {
+        self.durations.get(index).cloned().unwrap_or(None)
+    }
+
+    /// Returns the indexes of the frames marked with the specified tag.
+    pub fn get_tag(&self, name: &str) -> Option<Range<usize>> {
+        self.tags.get(name).cloned()
+    }
+
+    /// Returns how many frames there are.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Checks if there are no frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+/// What an animation does once it reaches its last frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum AnimationMode {
+    /// Starts again from the first frame.
+    Loop,
+    /// Plays backwards to the first frame, then forwards again.
+    PingPong,
+    /// Stays on the last frame.
+    Once,
+}
+
+/// Steps through a sequence of sprite sheet frames as time passes.
+///
+/// ```rust
+/// use leafrender::render::sprite::{AnimatedSprite, AnimationMode};
+///
+/// use std::time::Duration;
+///
+/// let mut walk = AnimatedSprite::new(vec![0, 1, 2, 3], Duration::from_millis(100), AnimationMode::Loop);
+///
+/// walk.update(Duration::from_millis(250));
+/// assert_eq!(walk.get_frame(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct AnimatedSprite {
+    frames: Vec<usize>,
+    durations: Vec<Duration>,
+    mode: AnimationMode,
+    elapsed: Duration,
+}
+
+impl AnimatedSprite {
+    /// Creates an animation through the specified frames, each shown for the same amount of
+    /// time.
+    pub fn new(frames: Vec<usize>, frame_duration: Duration, mode: AnimationMode) -> Self {
+        let durations = vec![frame_duration; frames.len()];
+
+        AnimatedSprite {
+            frames,
+            durations,
+            mode,
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    /// Creates an animation through the specified frames of a sprite sheet, using the
+    /// durations from the sheet where it has them, and the default duration otherwise.
+    pub fn from_sheet<T>(
+        sheet: &SpriteSheet<T>,
+        frames: Vec<usize>,
+        default_duration: Duration,
+        mode: AnimationMode,
+    ) -> Self {
+        let durations = frames
+            .iter()
+            .map(|&frame| sheet.get_duration(frame).unwrap_or(default_duration))
+            .collect();
+
+        AnimatedSprite {
+            frames,
+            durations,
+            mode,
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    /// Moves the animation forwards by the specified amount of time.
+    pub fn update(&mut self, delta: Duration) {
+        self.elapsed += delta;
+
+        // Keep the elapsed time within a single cycle, so it doesn't grow forever
+        let cycle = self.get_cycle_length();
+        if self.mode != AnimationMode::Once && cycle > Duration::from_secs(0) {
+            let elapsed = self.elapsed.as_nanos() % cycle.as_nanos();
+            self.elapsed = Duration::from_nanos(elapsed as u64);
+        }
+    }
+
+    /// Goes back to the start of the animation.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::from_secs(0);
+    }
+
+    /// Returns the sprite sheet index of the frame which should be shown.
+    pub fn get_frame(&self) -> usize {
+        let order = self.get_order();
+
+        let mut remaining = self.elapsed;
+        for &position in &order {
+            if remaining < self.durations[position] {
+                return self.frames[position];
+            }
+            remaining -= self.durations[position];
+        }
+
+        // Past the end, which only happens when playing once
+        order.last().map(|&x| self.frames[x]).unwrap_or(0)
+    }
+
+    /// Checks if an animation which is only played once has reached its end.
+    pub fn is_finished(&self) -> bool {
+        self.mode == AnimationMode::Once && self.elapsed >= self.get_cycle_length()
+    }
+
+    /// Returns the positions in the frame list which are played through in one cycle.
+    fn get_order(&self) -> Vec<usize> {
+        let count = self.frames.len();
+        let mut order: Vec<usize> = (0..count).collect();
+
+        // Play back down, without repeating the frames at either end
+        if self.mode == AnimationMode::PingPong && count > 2 {
+            order.extend((1..count - 1).rev());
+        }
+
+        order
+    }
+
+    /// Returns how long one cycle of the animation takes.
+    fn get_cycle_length(&self) -> Duration {
+        self.get_order()
+            .into_iter()
+            .map(|position| self.durations[position])
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for a 64x32 texture.
+    fn texture() -> AtlasRegion {
+        AtlasRegion::new(0, 0, 64, 32, 64, 32)
+    }
+
+    #[test]
+    fn reads_hash_layout() {
+        let json = r#"{
+            "frames": {
+                "idle.png": { "frame": { "x": 0, "y": 0, "w": 16, "h": 32 } },
+                "jump.png": { "frame": { "x": 16, "y": 8, "w": 24, "h": 24 } }
+            }
+        }"#;
+
+        let sheet = SpriteSheet::from_json(texture(), json).unwrap();
+        assert_eq!(sheet.len(), 2);
+
+        let jump = sheet.get_named_frame("jump.png").unwrap();
+        assert_eq!((jump.x, jump.y, jump.width, jump.height), (16, 8, 24, 24));
+        assert_eq!(
+            sheet
+                .get_frame(sheet.get_index("idle.png").unwrap())
+                .unwrap()
+                .height,
+            32
+        );
+        assert_eq!(sheet.get_duration(0), None);
+    }
+
+    #[test]
+    fn reads_array_layout_with_durations_and_tags() {
+        let json = r#"{
+            "frames": [
+                { "filename": "walk 0", "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+                { "filename": "walk 1", "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 150 },
+                { "filename": "walk 2", "frame": { "x": 32, "y": 0, "w": 16, "h": 16 } }
+            ],
+            "meta": {
+                "frameTags": [{ "name": "walk", "from": 1, "to": 2, "direction": "forward" }]
+            }
+        }"#;
+
+        let sheet = SpriteSheet::from_json(texture(), json).unwrap();
+        assert_eq!(sheet.len(), 3);
+        assert_eq!(sheet.get_index("walk 2"), Some(2));
+        assert_eq!(sheet.get_frame(1).unwrap().x, 16);
+        assert_eq!(sheet.get_duration(1), Some(Duration::from_millis(150)));
+        assert_eq!(sheet.get_duration(2), None);
+        assert_eq!(sheet.get_tag("walk"), Some(1..3));
+        assert_eq!(sheet.get_tag("run"), None);
+    }
+
+    #[test]
+    fn rejects_bad_sheets() {
+        let bad = [
+            "not json",
+            r#"{ "meta": {} }"#,
+            r#"{ "frames": [{ "filename": "a" }] }"#,
+            r#"{ "frames": [{ "frame": { "x": 0, "y": 0, "w": 16 } }] }"#,
+            r#"{ "frames": [{ "frame": { "x": -1, "y": 0, "w": 16, "h": 16 } }] }"#,
+            r#"{ "frames": [{ "frame": { "x": 56, "y": 0, "w": 16, "h": 16 } }] }"#,
+            r#"{ "frames": [{ "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "rotated": true }] }"#,
+            r#"{
+                "frames": [{ "frame": { "x": 0, "y": 0, "w": 16, "h": 16 } }],
+                "meta": { "frameTags": [{ "name": "walk", "from": 0, "to": 1 }] }
+            }"#,
+            r#"{
+                "frames": [{ "frame": { "x": 0, "y": 0, "w": 16, "h": 16 } }],
+                "meta": { "frameTags": [{ "name": "walk", "from": 1, "to": 0 }] }
+            }"#,
+        ];
+
+        for json in bad.iter() {
+            assert!(SpriteSheet::from_json(texture(), json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn rejects_frames_which_overflow() {
+        let json = format!(
+            r#"{{ "frames": [{{ "frame": {{ "x": {}, "y": 0, "w": 16, "h": 16 }} }}] }}"#,
+            u64::MAX
+        );
+        assert!(SpriteSheet::from_json(texture(), &json).is_err());
+
+        let json = format!(
+            r#"{{ "frames": [{{ "frame": {{ "x": 0, "y": 16, "w": 16, "h": {} }} }}] }}"#,
+            u64::MAX - 8
+        );
+        assert!(SpriteSheet::from_json(texture(), &json).is_err());
+    }
+
+    #[test]
+    fn rejects_tags_which_overflow() {
+        let json = format!(
+            r#"{{
+                "frames": [{{ "frame": {{ "x": 0, "y": 0, "w": 16, "h": 16 }} }}],
+                "meta": {{ "frameTags": [{{ "name": "walk", "from": 0, "to": {} }}] }}
+            }}"#,
+            u64::MAX
+        );
+        assert!(SpriteSheet::from_json(texture(), &json).is_err());
+    }
+}
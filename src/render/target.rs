@@ -0,0 +1,38 @@
+//! Textures which can be drawn into, instead of the screen.
+
+use crate::render::Dimensions;
+
+/// A texture which can be drawn into using `Drawer::set_render_target`, and then drawn
+/// itself using `get_texture`.
+///
+/// Targets are created using `Drawer::create_render_target`.
+pub struct RenderTarget<T> {
+    texture: T,
+}
+
+impl<T> RenderTarget<T> {
+    /// Returns the texture which is drawn into, so that it can be drawn elsewhere.
+    ///
+    /// A target can't be drawn while it is being drawn into.
+    pub fn get_texture(&self) -> &T {
+        &self.texture
+    }
+
+    /// Wraps a native texture. This is used by drawer implementations, and the texture
+    /// must be one that the drawer is able to draw into.
+    pub fn new(texture: T) -> Self {
+        RenderTarget { texture }
+    }
+}
+
+impl<T: Dimensions> Dimensions for RenderTarget<T> {
+    /// Returns the width of this target.
+    fn get_width(&self) -> usize {
+        self.texture.get_width()
+    }
+
+    /// Returns the height of this target.
+    fn get_height(&self) -> usize {
+        self.texture.get_height()
+    }
+}
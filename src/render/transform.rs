@@ -0,0 +1,178 @@
+//! 2D affine transforms, which are applied to positions and rectangles before they are
+//! drawn.
+
+use crate::pos::Rect;
+
+/// A 2D affine transformation matrix, which maps a point (x, y) to:
+///
+/// ```text
+/// x' = xx * x + xy * y + x0
+/// y' = yx * x + yy * y + y0
+/// ```
+///
+/// Coordinates are in pixels, with y pointing down the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub xx: f32,
+    pub xy: f32,
+    pub yx: f32,
+    pub yy: f32,
+    pub x0: f32,
+    pub y0: f32,
+}
+
+impl Transform {
+    /// Returns the transform which leaves points where they are.
+    pub fn identity() -> Self {
+        Transform {
+            xx: 1.0,
+            xy: 0.0,
+            yx: 0.0,
+            yy: 1.0,
+            x0: 0.0,
+            y0: 0.0,
+        }
+    }
+
+    /// Returns a transform which moves points by the specified amount.
+    pub fn translate(x: f32, y: f32) -> Self {
+        Transform {
+            x0: x,
+            y0: y,
+            ..Transform::identity()
+        }
+    }
+
+    /// Returns a transform which scales points away from the origin.
+    pub fn scale(x: f32, y: f32) -> Self {
+        Transform {
+            xx: x,
+            yy: y,
+            ..Transform::identity()
+        }
+    }
+
+    /// Returns a transform which rotates points clockwise (on screen) around the origin,
+    /// by the specified angle in radians.
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Transform {
+            xx: cos,
+            xy: -sin,
+            yx: sin,
+            yy: cos,
+            x0: 0.0,
+            y0: 0.0,
+        }
+    }
+
+    /// Returns a transform which rotates points clockwise (on screen) around the specified
+    /// point, by the specified angle in radians.
+    pub fn rotate_around(angle: f32, x: f32, y: f32) -> Self {
+        Transform::translate(x, y)
+            .then(&Transform::rotate(angle))
+            .then(&Transform::translate(-x, -y))
+    }
+
+    /// Combines this transform with another, which is applied to points before this one.
+    pub fn then(&self, other: &Transform) -> Self {
+        Transform {
+            xx: self.xx * other.xx + self.xy * other.yx,
+            xy: self.xx * other.xy + self.xy * other.yy,
+            yx: self.yx * other.xx + self.yy * other.yx,
+            yy: self.yx * other.xy + self.yy * other.yy,
+            x0: self.xx * other.x0 + self.xy * other.y0 + self.x0,
+            y0: self.yx * other.x0 + self.yy * other.y0 + self.y0,
+        }
+    }
+
+    /// Applies this transform to a point.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.xx * x + self.xy * y + self.x0,
+            self.yx * x + self.yy * y + self.y0,
+        )
+    }
+
+    /// Applies this transform to a rectangle, returning the smallest pixel aligned rectangle
+    /// which contains the result.
+    pub fn bounding_rect(&self, rect: &Rect) -> Rect {
+        let corners = [
+            self.apply(rect.x as f32, rect.y as f32),
+            self.apply((rect.x + rect.width) as f32, rect.y as f32),
+            self.apply(rect.x as f32, (rect.y + rect.height) as f32),
+            self.apply((rect.x + rect.width) as f32, (rect.y + rect.height) as f32),
+        ];
+
+        let mut min_x = corners[0].0;
+        let mut min_y = corners[0].1;
+        let mut max_x = corners[0].0;
+        let mut max_y = corners[0].1;
+
+        for &(x, y) in &corners[1..] {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+
+        let min_x = min_x.floor() as i32;
+        let min_y = min_y.floor() as i32;
+
+        Rect::new(
+            min_x,
+            min_y,
+            max_x.ceil() as i32 - min_x,
+            max_y.ceil() as i32 - min_y,
+        )
+    }
+
+    /// Returns roughly how much this transform stretches distances, taking the larger of
+    /// its two axes. Used to decide how finely curves need to be split up.
+    pub fn max_scale(&self) -> f32 {
+        let x_axis = (self.xx * self.xx + self.yx * self.yx).sqrt();
+        let y_axis = (self.xy * self.xy + self.yy * self.yy).sqrt();
+        x_axis.max(y_axis)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+/// A stack of transforms, each combined with the ones underneath it.
+pub(crate) struct TransformStack {
+    stack: Vec<Transform>,
+}
+
+impl TransformStack {
+    /// Returns the transform which is currently in effect.
+    pub fn get(&self) -> Transform {
+        self.stack.last().cloned().unwrap_or_default()
+    }
+
+    /// Combines the specified transform with the current one, and makes it current.
+    pub fn push(&mut self, transform: &Transform) {
+        let combined = self.get().then(transform);
+        self.stack.push(combined);
+    }
+
+    /// Restores the transform which was current before the last push. Does nothing if the
+    /// stack is empty.
+    pub fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Removes all transforms, restoring the identity transform.
+    pub fn clear(&mut self) {
+        self.stack.clear();
+    }
+
+    /// Creates a new, empty stack.
+    pub fn new() -> Self {
+        TransformStack { stack: Vec::new() }
+    }
+}
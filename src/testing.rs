@@ -0,0 +1,220 @@
+//! Golden-image snapshot testing for rendered frames.
+//!
+//! A frame is rendered against a headless drawer, then compared against a PNG baseline
+//! stored on disk. When the two differ by more than a per-channel tolerance, the rendered
+//! frame and a diff image are written next to the baseline for inspection.
+//!
+//! Set the `LEAFRENDER_BLESS` environment variable to `1` to (re)write baselines from the
+//! frames that are rendered, instead of comparing against them.
+//!
+//! ```no_run
+//! use leafrender::pos::Rect;
+//! use leafrender::render::Color;
+//! use leafrender::render::Drawer;
+//! use leafrender::testing::Snapshot;
+//! use leafrender::PlatformDrawer;
+//!
+//! Snapshot::new("red_square", 64, 64)
+//!     .with_tolerance(2)
+//!     .assert_matches::<PlatformDrawer, _>(|drawer| {
+//!         drawer.draw_colored_rect(&Rect::new(16, 16, 32, 32), &Color::new_3byte(255, 0, 0));
+//!     });
+//! ```
+
+use crate::render::Drawer;
+use crate::Error;
+
+use image::Rgba;
+use image::RgbaImage;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The environment variable which, when set to `1`, causes baselines to be rewritten.
+pub const BLESS_VAR: &str = "LEAFRENDER_BLESS";
+
+/// Describes a single snapshot, and how it should be compared.
+pub struct Snapshot {
+    name: String,
+    width: u32,
+    height: u32,
+    tolerance: u8,
+    baseline_dir: PathBuf,
+}
+
+/// Checks if baselines should be rewritten, rather than compared against.
+fn is_blessing() -> bool {
+    env::var(BLESS_VAR).map(|x| x == "1").unwrap_or(false)
+}
+
+/// Writes an image to disk, creating parent directories as needed.
+fn save_image(image: &RgbaImage, path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    image.save(path)?;
+    Ok(())
+}
+
+impl Snapshot {
+    /// Creates a new snapshot with the specified name and frame size. Baselines are
+    /// stored in `tests/snapshots` by default, with an exact match required.
+    pub fn new(name: &str, width: u32, height: u32) -> Self {
+        Snapshot {
+            name: name.to_string(),
+            width,
+            height,
+            tolerance: 0,
+            baseline_dir: PathBuf::from("tests/snapshots"),
+        }
+    }
+
+    /// Sets how far each channel of a pixel may stray from the baseline before the pixel
+    /// is considered to differ.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the directory which baselines are read from and written to.
+    pub fn with_baseline_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.baseline_dir = dir.into();
+        self
+    }
+
+    /// Returns the path of the baseline for this snapshot.
+    pub fn get_baseline_path(&self) -> PathBuf {
+        self.baseline_dir.join(format!("{}.png", self.name))
+    }
+
+    fn get_actual_path(&self) -> PathBuf {
+        self.baseline_dir.join(format!("{}.actual.png", self.name))
+    }
+
+    fn get_diff_path(&self) -> PathBuf {
+        self.baseline_dir.join(format!("{}.diff.png", self.name))
+    }
+
+    /// Renders a single frame with a new headless drawer. The frame is cleared to opaque
+    /// black before the closure is called.
+    pub fn render<D, F>(&self, draw: F) -> Result<RgbaImage, Error>
+    where
+        D: Drawer,
+        F: FnOnce(&mut D),
+    {
+        let mut drawer = D::new_headless(self.width, self.height)?;
+
+        drawer.start();
+        drawer.clear(false);
+
+        draw(&mut drawer);
+
+        let image = drawer.capture();
+        drawer.end()?;
+
+        Ok(image)
+    }
+
+    /// Compares a rendered frame against the baseline, or rewrites the baseline if
+    /// blessing is enabled.
+    pub fn compare(&self, image: &RgbaImage) -> Result<(), Error> {
+        let baseline_path = self.get_baseline_path();
+
+        if is_blessing() {
+            return save_image(image, &baseline_path);
+        }
+
+        let baseline = match image::open(&baseline_path) {
+            Ok(baseline) => baseline.to_rgba(),
+            Err(x) => {
+                save_image(image, &self.get_actual_path())?;
+                return Err(Error::Snapshot(format!(
+                    "Failed to read baseline {} ({}). Run with {}=1 to create it.",
+                    baseline_path.display(),
+                    x,
+                    BLESS_VAR
+                )));
+            }
+        };
+
+        if baseline.dimensions() != image.dimensions() {
+            save_image(image, &self.get_actual_path())?;
+            return Err(Error::Snapshot(format!(
+                "Snapshot {} is {:?}, but its baseline is {:?}",
+                self.name,
+                image.dimensions(),
+                baseline.dimensions()
+            )));
+        }
+
+        let mut diff = RgbaImage::new(image.width(), image.height());
+        let mut differing = 0;
+        let mut max_difference = 0;
+
+        for (x, y, actual) in image.enumerate_pixels() {
+            let expected = baseline.get_pixel(x, y);
+
+            let difference = actual
+                .data
+                .iter()
+                .zip(expected.data.iter())
+                .map(|(a, b)| a.max(b) - a.min(b))
+                .max()
+                .unwrap_or(0);
+
+            max_difference = max_difference.max(difference);
+
+            // Highlight differences in red, over a faded copy of the frame
+            if difference > self.tolerance {
+                differing += 1;
+                diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            } else {
+                let luma = (u16::from(actual.data[0])
+                    + u16::from(actual.data[1])
+                    + u16::from(actual.data[2]))
+                    / 3;
+                let faded = (luma / 4) as u8;
+                diff.put_pixel(x, y, Rgba([faded, faded, faded, 255]));
+            }
+        }
+
+        if differing == 0 {
+            // Clean up after any previous failures
+            let _ = fs::remove_file(self.get_actual_path());
+            let _ = fs::remove_file(self.get_diff_path());
+            return Ok(());
+        }
+
+        save_image(image, &self.get_actual_path())?;
+        save_image(&diff, &self.get_diff_path())?;
+
+        Err(Error::Snapshot(format!(
+            "Snapshot {} differs from its baseline in {} pixel(s) (largest channel \
+             difference {}, tolerance {}). See {}, or run with {}=1 to accept the change.",
+            self.name,
+            differing,
+            max_difference,
+            self.tolerance,
+            self.get_diff_path().display(),
+            BLESS_VAR
+        )))
+    }
+
+    /// Renders a frame and compares it against the baseline, panicking if they differ.
+    pub fn assert_matches<D, F>(&self, draw: F)
+    where
+        D: Drawer,
+        F: FnOnce(&mut D),
+    {
+        let result = self
+            .render::<D, F>(draw)
+            .and_then(|image| self.compare(&image));
+
+        if let Err(err) = result {
+            panic!("{}", err);
+        }
+    }
+}
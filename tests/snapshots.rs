@@ -0,0 +1,145 @@
+//! Renders each family of primitives with the software drawer, and compares the results
+//! against the baselines in `tests/snapshots`.
+//!
+//! Run with `LEAFRENDER_BLESS=1` to rewrite the baselines after an intended change.
+
+use leafrender::pos::Position;
+use leafrender::pos::Rect;
+use leafrender::render::gradient::Gradient;
+use leafrender::render::gradient::GradientStop;
+use leafrender::render::line::LineCap;
+use leafrender::render::line::LineJoin;
+use leafrender::render::path::FillRule;
+use leafrender::render::path::Path;
+use leafrender::render::software::drawer::SoftwareDrawer;
+use leafrender::render::transform::Transform;
+use leafrender::render::BlendMode;
+use leafrender::render::Color;
+use leafrender::render::Drawer;
+use leafrender::render::Texture;
+use leafrender::testing::Snapshot;
+
+#[test]
+fn rect() {
+    Snapshot::new("rect", 64, 64).assert_matches::<SoftwareDrawer, _>(|drawer| {
+        drawer.draw_colored_rect(&Rect::new(8, 8, 32, 24), &Color::new_3byte(255, 0, 0));
+
+        drawer.set_blend_mode(BlendMode::Alpha);
+        drawer.draw_colored_rect(
+            &Rect::new(24, 20, 32, 36),
+            &Color::new_4byte(0, 128, 255, 128),
+        );
+    });
+}
+
+#[test]
+fn texture() {
+    Snapshot::new("texture", 64, 64).assert_matches::<SoftwareDrawer, _>(|drawer| {
+        // A 4x4 checkerboard, stretched so that each texel covers 8x8 pixels
+        let mut checkerboard = Texture::new(4, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                let color = if (x + y) % 2 == 0 {
+                    Color::new_3byte(255, 255, 255)
+                } else {
+                    Color::new_3byte(0, 160, 0)
+                };
+                checkerboard.draw_pixel(&color, x, y);
+            }
+        }
+
+        let checkerboard = drawer.convert_native_texture(checkerboard);
+
+        drawer.draw_texture_sized(
+            &checkerboard,
+            &Rect::new(0, 0, 32, 32),
+            &Color::new_3byte(255, 255, 255),
+        );
+        drawer.draw_texture_sized(
+            &checkerboard,
+            &Rect::new(32, 32, 32, 32),
+            &Color::new_3byte(255, 128, 0),
+        );
+    });
+}
+
+#[test]
+fn line() {
+    Snapshot::new("line", 64, 64).assert_matches::<SoftwareDrawer, _>(|drawer| {
+        drawer.draw_line(
+            &Position::new(4, 4),
+            &Position::new(60, 28),
+            3.0,
+            &Color::new_3byte(255, 255, 0),
+        );
+
+        drawer.draw_polyline(
+            &[
+                Position::new(8, 56),
+                Position::new(24, 36),
+                Position::new(40, 56),
+                Position::new(56, 36),
+            ],
+            5.0,
+            &Color::new_3byte(0, 255, 255),
+            LineJoin::Round,
+            LineCap::Square,
+        );
+    });
+}
+
+#[test]
+fn path() {
+    Snapshot::new("path", 64, 64).assert_matches::<SoftwareDrawer, _>(|drawer| {
+        // A five-pointed star, which overlaps itself in the middle
+        let mut star = Path::new();
+        for i in 0..5 {
+            let angle = (i as f32 * 144.0 - 90.0).to_radians();
+            let (x, y) = (16.0 + angle.cos() * 14.0, 16.0 + angle.sin() * 14.0);
+            star = if i == 0 {
+                star.move_to(x, y)
+            } else {
+                star.line_to(x, y)
+            };
+        }
+        let star = star.close();
+
+        drawer.fill_path(&star, FillRule::NonZero, &Color::new_3byte(255, 0, 255));
+
+        drawer.push_transform(&Transform::translate(32.0, 0.0));
+        drawer.fill_path(&star, FillRule::EvenOdd, &Color::new_3byte(255, 0, 255));
+        drawer.pop_transform();
+
+        let curve = Path::new()
+            .move_to(4.0, 60.0)
+            .quad_to(32.0, 20.0, 60.0, 60.0)
+            .cubic_to(40.0, 48.0, 24.0, 48.0, 4.0, 60.0);
+
+        drawer.stroke_path(
+            &curve,
+            2.0,
+            &Color::new_3byte(255, 255, 255),
+            LineJoin::Miter,
+            LineCap::Butt,
+        );
+    });
+}
+
+#[test]
+fn gradient() {
+    Snapshot::new("gradient", 64, 64)
+        .with_tolerance(1)
+        .assert_matches::<SoftwareDrawer, _>(|drawer| {
+            let stops = vec![
+                GradientStop::new(0.0, Color::new_3byte(255, 0, 0)),
+                GradientStop::new(0.5, Color::new_3byte(0, 255, 0)),
+                GradientStop::new(1.0, Color::new_3byte(0, 0, 255)),
+            ];
+
+            let linear = Gradient::linear(Position::new(0, 0), Position::new(64, 0), stops.clone());
+            drawer.draw_gradient_rect(&Rect::new(0, 0, 64, 24), &linear);
+
+            let radial = Gradient::radial(Position::new(32, 44), 20.0, stops);
+            drawer.draw_gradient_rect(&Rect::new(0, 24, 64, 40), &radial);
+        });
+}